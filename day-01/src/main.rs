@@ -24,7 +24,6 @@ fn part_1(filename: &str) -> u32 {
 
     reader
         .lines()
-        .into_iter()
         .map(|line| line.unwrap().parse::<u32>().unwrap())
         .map(base_fuel)
         .sum()
@@ -36,7 +35,6 @@ fn part_2(filename: &str) -> u32 {
 
     reader
         .lines()
-        .into_iter()
         .map(|line| line.unwrap().parse::<u32>().unwrap())
         .flat_map(fuel_components)
         .sum()
@@ -1,66 +1,100 @@
 #[macro_use]
 extern crate itertools;
 
-const ADD: usize = 1;
-const MULTIPLY: usize = 2;
-const HALT: usize = 99;
-
-fn execute(mut program: Vec<usize>) -> usize {
-    let mut pc = 0;
-
-    loop {
-        match program[pc] {
-            HALT => return program[0],
-            ADD => {
-                let a = program[pc + 1];
-                let b = program[pc + 2];
-                let c = program[pc + 3];
-                program[c] = program[a] + program[b];
-                pc += 4;
-            }
-            MULTIPLY => {
-                let a = program[pc + 1];
-                let b = program[pc + 2];
-                let c = program[pc + 3];
-                program[c] = program[a] * program[b];
-                pc += 4;
-            }
-            op => panic!("Unknown opcode {}", op),
+use std::io;
+use std::iter;
+
+use intcode::profile::Profile;
+use intcode::symbolic::{Expr, SymbolicMachine};
+use intcode::{search, Backend, Machine, Program};
+
+fn execute(prototype: &Machine, noun: i64, verb: i64) -> i64 {
+    let mut machine = prototype.clone();
+    machine.memory.set(1, noun).unwrap();
+    machine.memory.set(2, verb).unwrap();
+    for _ in machine.execute(iter::empty()) {}
+    machine.memory.get(0)
+}
+
+fn part_1(prototype: &Machine) -> i64 {
+    execute(prototype, 12, 2)
+}
+
+/// Solves for noun and verb symbolically, if the result turns out to be a
+/// polynomial of them.
+fn part_2_symbolic(program: &Program) -> Option<i64> {
+    let mut machine = SymbolicMachine::new(program);
+    machine.set(1, Expr::var("noun"));
+    machine.set(2, Expr::var("verb"));
+    machine.run().ok()?;
+
+    let result = machine.get(0).to_polynomial()?;
+    let solutions = result.solve(19690720, &[("noun", 0..100), ("verb", 0..100)]);
+    solutions.first().map(|nv| 100 * nv[0] + nv[1])
+}
+
+fn part_2(prototype: &Machine) -> i64 {
+    let candidates: Vec<_> = iproduct!(0..100, 0..100).collect();
+
+    let found = search::find_first(&candidates, search::threads(), |&(noun, verb)| {
+        match execute(prototype, noun, verb) {
+            19690720 => Some(100 * noun + verb),
+            _ => None,
         }
+    });
+
+    match found {
+        Some((_, res)) => res,
+        None => panic!("No solution found!"),
     }
 }
 
-fn part_1(program: &Vec<usize>) -> usize {
-    let mut program = program.clone();
-    program[1] = 12;
-    program[2] = 2;
-    execute(program)
-}
+/// Profiles every run of the brute-force search, merged into one profile.
+fn profile_part_2(prototype: &Machine) -> Profile {
+    let mut total = Profile::new();
 
-fn part_2(program: &Vec<usize>) -> usize {
     for (noun, verb) in iproduct!(0..100, 0..100) {
-        let mut program = program.clone();
-        program[1] = noun;
-        program[2] = verb;
-        if execute(program) == 19690720 {
-            return 100 * noun + verb;
-        }
+        let mut machine = prototype.clone();
+        machine.profile = Some(Profile::new());
+        machine.memory.set(1, noun).unwrap();
+        machine.memory.set(2, verb).unwrap();
+        for _ in machine.execute(iter::empty()) {}
+        total.merge(machine.profile.as_ref().unwrap());
     }
 
-    panic!("No solution found!")
+    total
 }
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
+fn main() -> io::Result<()> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let profile = args.iter().any(|arg| arg == "--profile");
+    args.retain(|arg| arg != "--profile");
     let filename = &args[1];
 
-    let program: Vec<usize> = std::fs::read_to_string(filename)
-        .unwrap()
-        .split(",")
-        .map(|s| s.trim())
-        .map(|s| usize::from_str_radix(s, 10).unwrap())
-        .collect();
+    let backend = match args.get(2).map(String::as_str) {
+        None | Some("interpreter") => Backend::Interpreter,
+        Some("compiled") => Backend::Compiled,
+        Some(other) => panic!("Unknown backend {}", other),
+    };
+
+    // every run is cloned from here so that they share decoded and compiled code
+    let program = intcode::load(filename)?;
+    let mut prototype = Machine::new(program.clone());
+    prototype.backend = backend;
+    prototype.memory.prepare_cache();
+
+    println!("part-1 = {}", part_1(&prototype));
+    let part2 = match part_2_symbolic(&program) {
+        Some(res) => res,
+        None => part_2(&prototype),
+    };
+
+    println!("part-2 = {}", part2);
+
+    if profile {
+        println!();
+        profile_part_2(&prototype).write_report(io::stdout(), 10)?;
+    }
 
-    println!("part-1 = {}", part_1(&program));
-    println!("part-2 = {}", part_2(&program));
+    Ok(())
 }
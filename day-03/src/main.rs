@@ -22,7 +22,7 @@ fn parse_wire(raw: &str) -> Wire {
     raw.split(",")
         .map(|s| s.trim())
         .map(|s| s.split_at(1))
-        .map(|(dir, n)| (parse_direction(dir), i32::from_str_radix(n, 10).unwrap()))
+        .map(|(dir, n)| (parse_direction(dir), n.parse::<i32>().unwrap()))
         .collect()
 }
 
@@ -71,8 +71,8 @@ fn draw(wire: &Wire) -> DrawnWire {
 }
 
 fn manhattan(c0: (i32, i32), c1: (i32, i32)) -> u32 {
-    let dx = (c0.0 - c1.0).abs() as u32;
-    let dy = (c0.1 - c1.1).abs() as u32;
+    let dx = (c0.0 - c1.0).unsigned_abs();
+    let dy = (c0.1 - c1.1).unsigned_abs();
     dx + dy
 }
 
@@ -105,7 +105,6 @@ fn main() -> io::Result<()> {
 
     let wires: Vec<Wire> = reader
         .lines()
-        .into_iter()
         .map(|line| parse_wire(&line.unwrap()))
         .collect();
 
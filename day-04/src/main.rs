@@ -24,7 +24,7 @@ fn groups(mut n: u32) -> impl Iterator<Item = (u32, u32)> {
         0 => None,
         digit @ 1..=9 => {
             n = 0;
-            return Some((digit, 1));
+            Some((digit, 1))
         }
         _ => {
             let digit = n % 10;
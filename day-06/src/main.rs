@@ -10,7 +10,6 @@ fn read_graph(filename: &str) -> Graph {
 
     reader
         .lines()
-        .into_iter()
         .map(|line| {
             let line = line.unwrap();
             let mut components = line.splitn(2, ')');
@@ -45,13 +44,13 @@ fn ancestors<'a>(graph: &'a Graph, mut object: String) -> impl Iterator<Item = S
 fn part_1(graph: &Graph) -> u32 {
     graph
         .keys()
-        .map(|object| num_orbits(&graph, object.to_string()))
+        .map(|object| num_orbits(graph, object.to_string()))
         .sum()
 }
 
 fn part_2(graph: &Graph) -> usize {
-    let my_orbits: Vec<_> = ancestors(&graph, "YOU".to_string()).collect();
-    let santas_orbits: Vec<_> = ancestors(&graph, "SAN".to_string()).collect();
+    let my_orbits: Vec<_> = ancestors(graph, "YOU".to_string()).collect();
+    let santas_orbits: Vec<_> = ancestors(graph, "SAN".to_string()).collect();
 
     let mut ii = my_orbits.len() - 1;
     let mut jj = santas_orbits.len() - 1;
@@ -9,15 +9,15 @@ fn count(array: &[u32], target: u32) -> usize {
     array.iter().filter(|x| **x == target).count()
 }
 
-fn part_1(raw: &Vec<u32>) -> usize {
+fn part_1(raw: &[u32]) -> usize {
     raw.chunks((WIDTH * HEIGHT) as usize)
-        .map(|layer| (count(&layer, 0), count(&layer, 1), count(&layer, 2)))
-        .min_by_key(|(cnt_0, _, _)| cnt_0.clone())
+        .map(|layer| (count(layer, 0), count(layer, 1), count(layer, 2)))
+        .min_by_key(|(cnt_0, _, _)| *cnt_0)
         .map(|(_, cnt_1, cnt_2)| cnt_1 * cnt_2)
         .unwrap()
 }
 
-fn part_2(raw: &Vec<u32>) {
+fn part_2(raw: &[u32]) {
     let mut res = [TRANSPARENT; (WIDTH * HEIGHT) as usize];
 
     for layer in raw.chunks((WIDTH * HEIGHT) as usize) {
@@ -36,7 +36,7 @@ fn part_2(raw: &Vec<u32>) {
                 _ => print!(" "),
             }
         }
-        print!("\n");
+        println!();
     }
 }
 
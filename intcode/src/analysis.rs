@@ -0,0 +1,334 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use crate::disasm::{self, Item, Operand};
+use crate::{Instruction, Mode};
+
+/// A maximal run of instructions that is only entered at the top and only
+/// left at the bottom.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    /// The address just past the last instruction.
+    pub end: usize,
+    /// Start addresses of the blocks control may flow to next.
+    pub successors: Vec<usize>,
+    /// Whether the block ends in a jump whose target could not be resolved.
+    pub unresolved: bool,
+}
+
+/// The result of analysing a program without running it.
+///
+/// Jumps through memory (`jt 1, [x]`) are resolved when nothing reachable
+/// writes to `x` or to the jump itself in position mode. Writes in relative
+/// mode are assumed to go to the stack beyond the program, so they are
+/// ignored when resolving jumps and looking for writes into code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Analysis {
+    pub blocks: BTreeMap<usize, Block>,
+    /// Addresses jumped to by reachable jumps.
+    pub jump_targets: BTreeSet<usize>,
+    /// Jumps that do not use an immediate target, and where they go if that
+    /// could be determined.
+    pub computed_jumps: BTreeMap<usize, Option<usize>>,
+    /// Address ranges not covered by any reachable instruction, as
+    /// `(start, end)`.
+    pub unreachable: Vec<(usize, usize)>,
+    /// Reachable instructions that write into reachable code, as `(pc, addr)`.
+    /// Reachable addresses that do not decode count as code.
+    pub code_writes: Vec<(usize, usize)>,
+}
+
+type Code = BTreeMap<usize, (Instruction, Vec<Operand>)>;
+
+/// Instructions reachable from address 0, following `resolved` computed
+/// jumps as well as immediate ones. Also returns the reachable addresses
+/// that do not hold a valid instruction, usually because the program
+/// patches them before getting there.
+fn reachable(program: &[i64], resolved: &BTreeMap<usize, Option<usize>>) -> (Code, BTreeSet<usize>) {
+    let mut code = BTreeMap::new();
+    let mut invalid = BTreeSet::new();
+    let mut pending = vec![0];
+
+    while let Some(addr) = pending.pop() {
+        if code.contains_key(&addr) || invalid.contains(&addr) {
+            continue;
+        }
+
+        match disasm::decode(program, addr) {
+            Some(Item::Code(instruction, operands)) => {
+                pending.extend(disasm::successors(addr, &instruction, &operands));
+                pending.extend(resolved.get(&addr).cloned().flatten());
+                code.insert(addr, (instruction, operands));
+            }
+            _ => {
+                invalid.insert(addr);
+            }
+        }
+    }
+
+    (code, invalid)
+}
+
+/// The address written by an instruction, if it is known statically.
+fn written(instruction: &Instruction, operands: &[Operand]) -> Option<usize> {
+    let operand = operands[instruction.written_param()?];
+
+    match operand.mode {
+        Mode::Position if operand.value >= 0 => Some(operand.value as usize),
+        _ => None,
+    }
+}
+
+/// The jump target operand of a conditional jump that may be taken, unless
+/// it is immediate.
+fn computed_target(instruction: &Instruction, operands: &[Operand]) -> Option<Operand> {
+    let jumps_if_true = match instruction {
+        Instruction::JumpIfTrue(..) => true,
+        Instruction::JumpIfFalse(..) => false,
+        _ => return None,
+    };
+
+    let (cond, target) = (operands[0], operands[1]);
+
+    match (cond.mode, target.mode) {
+        (Mode::Value, _) if (cond.value != 0) != jumps_if_true => None,
+        (_, Mode::Value) => None,
+        _ => Some(target),
+    }
+}
+
+fn resolve(program: &[i64], code: &Code) -> BTreeMap<usize, Option<usize>> {
+    let writes: BTreeSet<usize> = code.values().filter_map(|(i, operands)| written(i, operands)).collect();
+
+    code.iter()
+        .filter_map(|(addr, (instruction, operands))| {
+            let target = computed_target(instruction, operands)?;
+            let patched = (*addr..addr + instruction.size()).any(|a| writes.contains(&a));
+
+            let resolved = match target.mode {
+                _ if patched => None,
+                Mode::Position if target.value >= 0 && !writes.contains(&(target.value as usize)) => {
+                    program.get(target.value as usize).filter(|x| **x >= 0).map(|x| *x as usize)
+                }
+                _ => None,
+            };
+
+            Some((*addr, resolved))
+        })
+        .collect()
+}
+
+pub fn analyze(program: &[i64]) -> Analysis {
+    let mut computed_jumps = BTreeMap::new();
+    let (mut code, mut invalid) = reachable(program, &computed_jumps);
+
+    // newly reachable code may write to resolved targets, so iterate, but
+    // give up on programs that keep flip-flopping
+    for _ in 0..16 {
+        let resolved = resolve(program, &code);
+
+        if resolved == computed_jumps {
+            break;
+        }
+
+        computed_jumps = resolved;
+        (code, invalid) = reachable(program, &computed_jumps);
+    }
+
+    let mut jump_targets = BTreeSet::new();
+
+    for (addr, (instruction, operands)) in &code {
+        if matches!(instruction, Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..)) {
+            let next = addr + instruction.size();
+            let targets = disasm::successors(*addr, instruction, operands).into_iter().filter(|a| *a != next);
+            jump_targets.extend(targets);
+            jump_targets.extend(computed_jumps.get(addr).cloned().flatten());
+        }
+    }
+
+    let mut covered: BTreeSet<usize> = code.iter().flat_map(|(addr, (i, _))| *addr..addr + i.size()).collect();
+    covered.extend(invalid);
+
+    let code_writes = code
+        .iter()
+        .filter_map(|(addr, (i, operands))| written(i, operands).map(|target| (*addr, target)))
+        .filter(|(_, target)| covered.contains(target))
+        .collect();
+
+    let mut unreachable: Vec<(usize, usize)> = vec![];
+
+    for addr in (0..program.len()).filter(|addr| !covered.contains(addr)) {
+        match unreachable.last_mut() {
+            Some((_, end)) if *end == addr => *end += 1,
+            _ => unreachable.push((addr, addr + 1)),
+        }
+    }
+
+    Analysis {
+        blocks: blocks(&code, &jump_targets, &computed_jumps),
+        jump_targets,
+        computed_jumps,
+        unreachable,
+        code_writes,
+    }
+}
+
+fn blocks(code: &Code, jump_targets: &BTreeSet<usize>, computed_jumps: &BTreeMap<usize, Option<usize>>) -> BTreeMap<usize, Block> {
+    let ends_block = |i: &Instruction| {
+        matches!(i, Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) | Instruction::Halt)
+    };
+
+    let mut blocks = BTreeMap::new();
+    let mut current: Option<Block> = None;
+
+    for (addr, (instruction, operands)) in code {
+        let next = addr + instruction.size();
+
+        let mut block = match current.take() {
+            Some(block) if block.end == *addr && !jump_targets.contains(addr) => block,
+            Some(mut block) => {
+                if block.end == *addr {
+                    block.successors.push(*addr);
+                }
+
+                blocks.insert(block.start, block);
+                Block { start: *addr, end: *addr, successors: vec![], unresolved: false }
+            }
+            None => Block { start: *addr, end: *addr, successors: vec![], unresolved: false },
+        };
+
+        block.end = next;
+
+        if ends_block(instruction) {
+            block.successors = disasm::successors(*addr, instruction, operands);
+            block.successors.extend(computed_jumps.get(addr).cloned().flatten());
+            block.unresolved = computed_jumps.get(addr) == Some(&None);
+            block.successors.retain(|a| code.contains_key(a));
+            block.successors.dedup();
+            blocks.insert(block.start, block);
+        } else {
+            current = Some(block);
+        }
+    }
+
+    if let Some(mut block) = current {
+        if code.contains_key(&block.end) {
+            block.successors.push(block.end);
+        }
+
+        blocks.insert(block.start, block);
+    }
+
+    blocks
+}
+
+impl Analysis {
+    /// Writes the control-flow graph in Graphviz format, listing the
+    /// instructions of each block. Instructions that write into code are
+    /// marked, and unresolved jumps point to a `?` node.
+    pub fn write_dot(&self, program: &[i64], out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "digraph cfg {{")?;
+        writeln!(out, "    node [shape=box, fontname=monospace];")?;
+
+        let writes_code: BTreeSet<usize> = self.code_writes.iter().map(|(pc, _)| *pc).collect();
+
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            let mut addr = block.start;
+
+            while let Some(item) = disasm::decode(program, addr).filter(|_| addr < block.end) {
+                let marker = if writes_code.contains(&addr) { "  ; writes code" } else { "" };
+                label += &format!("{}: {}{}\\l", addr, item, marker);
+                addr += item.size();
+            }
+
+            writeln!(out, "    b{} [label=\"{}\"];", block.start, label)?;
+
+            for succ in &block.successors {
+                writeln!(out, "    b{} -> b{};", block.start, succ)?;
+            }
+
+            if block.unresolved {
+                writeln!(out, "    b{} -> unknown;", block.start)?;
+            }
+        }
+
+        if self.blocks.values().any(|block| block.unresolved) {
+            writeln!(out, "    unknown [shape=ellipse, label=\"?\"];")?;
+        }
+
+        writeln!(out, "}}")
+    }
+
+    pub fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        let list = |items: Vec<String>| format!("[{}]", items.join(", "));
+
+        let blocks = self
+            .blocks
+            .values()
+            .map(|b| {
+                let succ = list(b.successors.iter().map(|s| s.to_string()).collect());
+                format!(
+                    "{{\"start\": {}, \"end\": {}, \"successors\": {}, \"unresolved\": {}}}",
+                    b.start, b.end, succ, b.unresolved
+                )
+            })
+            .collect();
+
+        let computed = self
+            .computed_jumps
+            .iter()
+            .map(|(pc, target)| match target {
+                Some(target) => format!("{{\"pc\": {}, \"target\": {}}}", pc, target),
+                None => format!("{{\"pc\": {}, \"target\": null}}", pc),
+            })
+            .collect();
+
+        let ranges = |ranges: &[(usize, usize)], a: &str, b: &str| {
+            list(ranges.iter().map(|(x, y)| format!("{{\"{}\": {}, \"{}\": {}}}", a, x, b, y)).collect())
+        };
+
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"blocks\": {},", list(blocks))?;
+        writeln!(out, "  \"jump_targets\": {},", list(self.jump_targets.iter().map(|a| a.to_string()).collect()))?;
+        writeln!(out, "  \"computed_jumps\": {},", list(computed))?;
+        writeln!(out, "  \"unreachable\": {},", ranges(&self.unreachable, "start", "end"))?;
+        writeln!(out, "  \"code_writes\": {}", ranges(&self.code_writes, "pc", "addr"))?;
+        writeln!(out, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_analyze() {
+        let program = assemble(
+            "
+                in [x]
+                jt [x], [target]    ; computed, but [target] is never written
+                out 0
+                hlt
+            yes: add [x], 1, [patch + 1]
+            patch: out 0
+                hlt
+                .data 7, 7
+            target: .data yes
+            x: .data 0
+        ",
+        )
+        .unwrap();
+
+        let analysis = analyze(&program);
+
+        assert_eq!(analysis.computed_jumps, vec![(2, Some(8))].into_iter().collect());
+        assert_eq!(analysis.jump_targets, vec![8].into_iter().collect());
+        assert_eq!(analysis.blocks.keys().cloned().collect::<Vec<_>>(), vec![0, 5, 8]);
+        assert_eq!(analysis.blocks[&0].successors, vec![5, 8]);
+        assert_eq!(analysis.code_writes, vec![(8, 13)]);
+        assert_eq!(analysis.unreachable, vec![(15, 19)]);
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+
+use crate::{IntcodeError, Machine, Word, Yield};
+
+/// A run of output, either text or a value outside the ASCII range.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Output<W = i64> {
+    Text(String),
+    Value(W),
+}
+
+impl<W: Word> fmt::Display for Output<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Output::Text(text) => write!(f, "{}", text),
+            Output::Value(val) => write!(f, "{}", val),
+        }
+    }
+}
+
+/// Converts a line of text to character codes, adding the newline.
+pub fn encode<W: Word>(line: &str) -> impl Iterator<Item = W> + '_ {
+    line.chars().chain(Some('\n')).map(|c| W::from_i64(c as i64))
+}
+
+/// Groups output codes into runs of text, passing through values that are
+/// not ASCII.
+pub fn render<W: Word>(codes: &[W]) -> Vec<Output<W>> {
+    let mut res: Vec<Output<W>> = vec![];
+
+    for code in codes {
+        let c = match code.to_i64() {
+            Some(c @ 0..=127) => c as u8 as char,
+            _ => {
+                res.push(Output::Value(code.clone()));
+                continue;
+            }
+        };
+
+        match res.last_mut() {
+            Some(Output::Text(text)) => text.push(c),
+            _ => res.push(Output::Text(c.to_string())),
+        }
+    }
+
+    res
+}
+
+/// Wraps a machine that takes commands as lines of text and answers in text.
+pub struct Ascii<W = i64> {
+    pub machine: Machine<W>,
+}
+
+impl<W: Word> Ascii<W> {
+    pub fn new(machine: Machine<W>) -> Self {
+        Ascii { machine }
+    }
+
+    /// Queues up a line of input.
+    pub fn send(&mut self, line: &str) {
+        self.machine.input.extend(encode(line));
+    }
+
+    /// Runs the machine until it halts or needs more input, returning its
+    /// output.
+    pub fn run(&mut self) -> Result<Vec<Output<W>>, IntcodeError<W>> {
+        let mut codes = vec![];
+
+        while let Yield::Output(val) = self.machine.run()? {
+            codes.push(val);
+        }
+
+        Ok(render(&codes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii() {
+        // echoes a line, then outputs 1000
+        let program = crate::asm::assemble(
+            "
+            loop:
+                in [c]
+                out [c]
+                eq [c], 10, [t]
+                jf [t], loop
+                out 1000
+                hlt
+            c: .data 0
+            t: .data 0
+        ",
+        )
+        .unwrap();
+
+        let mut ascii = Ascii::new(Machine::new(program));
+        assert_eq!(ascii.run(), Ok(vec![]));
+
+        ascii.send("hello");
+        assert_eq!(ascii.run(), Ok(vec![Output::Text("hello\n".to_string()), Output::Value(1000)]));
+        assert!(ascii.machine.halted);
+    }
+}
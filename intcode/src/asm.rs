@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{Instruction, Mode, Program};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+enum Statement<'a> {
+    Code(&'a str, Vec<&'a str>),
+    Data(Vec<&'a str>),
+}
+
+/// Builds an instruction from its mnemonic and the modes of its operands.
+fn build(mnemonic: &str, modes: &[Mode]) -> Result<Instruction, String> {
+    use Instruction::*;
+
+    let instruction = match (mnemonic, modes) {
+        ("add", &[m0, m1, m2]) => Add(m0, m1, m2),
+        ("mul", &[m0, m1, m2]) => Multiply(m0, m1, m2),
+        ("in", &[m0]) => Input(m0),
+        ("out", &[m0]) => Output(m0),
+        ("jt", &[m0, m1]) => JumpIfTrue(m0, m1),
+        ("jf", &[m0, m1]) => JumpIfFalse(m0, m1),
+        ("lt", &[m0, m1, m2]) => LessThan(m0, m1, m2),
+        ("eq", &[m0, m1, m2]) => Equals(m0, m1, m2),
+        ("arb", &[m0]) => AdjustBase(m0),
+        ("hlt", &[]) => Halt,
+        ("add", _) | ("mul", _) | ("in", _) | ("out", _) | ("jt", _) | ("jf", _) | ("lt", _) | ("eq", _) | ("arb", _) | ("hlt", _) => {
+            return Err(format!("Wrong number of operands for {}", mnemonic))
+        }
+        _ => return Err(format!("Unknown mnemonic {}", mnemonic)),
+    };
+
+    Ok(instruction)
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+/// Evaluates a sum of numbers and symbols such as `loop + 2` or `-1`.
+fn eval(expr: &str, symbols: &HashMap<&str, i64>) -> Result<i64, String> {
+    let mut rest = expr.trim();
+    let mut sign = 1;
+    let mut total: i64 = 0;
+
+    if let Some(r) = rest.strip_prefix('-') {
+        sign = -1;
+        rest = r;
+    } else if let Some(r) = rest.strip_prefix('+') {
+        rest = r;
+    }
+
+    loop {
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let term = rest[..end].trim();
+
+        let value = match term.parse::<i64>() {
+            Ok(x) => x,
+            Err(_) if is_identifier(term) => match symbols.get(term) {
+                Some(x) => *x,
+                None => return Err(format!("Unknown symbol {}", term)),
+            },
+            Err(_) => return Err(format!("Invalid expression {}", expr.trim())),
+        };
+
+        total = value
+            .checked_mul(sign)
+            .and_then(|x| total.checked_add(x))
+            .ok_or_else(|| format!("Overflow in {}", expr.trim()))?;
+
+        if end == rest.len() {
+            return Ok(total);
+        }
+
+        sign = if rest[end..].starts_with('-') { -1 } else { 1 };
+        rest = &rest[end + 1..];
+    }
+}
+
+/// Splits an operand into its mode and the expression for its value.
+fn operand(s: &str) -> (Mode, &str) {
+    let s = s.trim();
+
+    let inner = match s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Some(inner) => inner.trim(),
+        None => return (Mode::Value, s),
+    };
+
+    match inner.strip_prefix("rb") {
+        Some(rest) if rest.trim().is_empty() => (Mode::Relative, "0"),
+        Some(rest) if rest.trim_start().starts_with(['+', '-']) => (Mode::Relative, rest),
+        _ => (Mode::Position, inner),
+    }
+}
+
+fn split_operands(s: &str) -> Vec<&str> {
+    match s.trim() {
+        "" => vec![],
+        s => s.split(',').map(|s| s.trim()).collect(),
+    }
+}
+
+/// Assembles source in the syntax produced by the disassembler.
+///
+/// Each line holds an optional label (`name:`), then an instruction or a
+/// directive, then an optional `;` comment. Operands are `x` (value mode),
+/// `[x]` (position mode) or `[rb+x]` (relative mode), where `x` is a sum of
+/// numbers, labels and constants. Directives are `.data x, y, ...` and
+/// `.const NAME = x`. A numeric label like `12:` asserts the current address,
+/// which lets disassembler output be assembled back as-is.
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+    let mut symbols: HashMap<&str, i64> = HashMap::new();
+    let mut statements = vec![];
+    let mut addr = 0;
+
+    for (ii, line) in source.lines().enumerate() {
+        let error = |message| AsmError { line: ii + 1, message };
+
+        let mut rest = line.split(';').next().unwrap().trim();
+
+        while let Some(colon) = rest.find(':') {
+            let label = rest[..colon].trim();
+
+            if let Ok(expected) = label.parse::<usize>() {
+                if expected != addr {
+                    return Err(error(format!("Expected address {}, found {}", expected, addr)));
+                }
+            } else if is_identifier(label) && label != "rb" {
+                if symbols.insert(label, addr as i64).is_some() {
+                    return Err(error(format!("Duplicate symbol {}", label)));
+                }
+            } else {
+                break;
+            }
+
+            rest = rest[colon + 1..].trim();
+        }
+
+        if rest.is_empty() {
+            continue;
+        }
+
+        let (head, tail) = match rest.find(char::is_whitespace) {
+            Some(pos) => (&rest[..pos], &rest[pos..]),
+            None => (rest, ""),
+        };
+
+        match head {
+            ".const" => {
+                let mut parts = tail.splitn(2, '=');
+                let name = parts.next().unwrap().trim();
+                let expr = parts.next().ok_or_else(|| error("Expected .const NAME = value".to_string()))?;
+
+                if !is_identifier(name) || name == "rb" {
+                    return Err(error(format!("Invalid constant name {}", name)));
+                }
+
+                let value = eval(expr, &symbols).map_err(error)?;
+
+                if symbols.insert(name, value).is_some() {
+                    return Err(error(format!("Duplicate symbol {}", name)));
+                }
+            }
+            ".data" => {
+                let values = split_operands(tail);
+                addr += values.len();
+                statements.push((ii + 1, Statement::Data(values)));
+            }
+            mnemonic => {
+                let operands = split_operands(tail);
+                let modes: Vec<_> = operands.iter().map(|s| operand(s).0).collect();
+                addr += build(mnemonic, &modes).map_err(error)?.size();
+                statements.push((ii + 1, Statement::Code(mnemonic, operands)));
+            }
+        }
+    }
+
+    let mut program = vec![];
+
+    for (line, statement) in statements {
+        let error = |message| AsmError { line, message };
+
+        match statement {
+            Statement::Data(values) => {
+                for value in values {
+                    program.push(eval(value, &symbols).map_err(error)?);
+                }
+            }
+            Statement::Code(mnemonic, operands) => {
+                let operands: Vec<_> = operands.into_iter().map(operand).collect();
+                let modes: Vec<_> = operands.iter().map(|(mode, _)| *mode).collect();
+
+                program.push(build(mnemonic, &modes).map_err(error)?.encode());
+
+                for (_, expr) in operands {
+                    program.push(eval(expr, &symbols).map_err(error)?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm;
+
+    fn round_trip(program: &[i64]) -> Program {
+        let source: String = disasm::disassemble(program)
+            .into_iter()
+            .map(|(addr, item)| format!("{}: {}\n", addr, item))
+            .collect();
+
+        assemble(&source).unwrap()
+    }
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            .const TARGET = 8
+                in [value]          ; read a number
+                eq [value], TARGET, [value]
+                jt [value], equal
+                out 0
+                hlt
+            equal: out [rb+1]
+                hlt
+            value: .data -1
+        ";
+
+        assert_eq!(assemble(source), Ok(vec![3, 15, 1008, 15, 8, 15, 1005, 15, 12, 104, 0, 99, 204, 1, 99, -1]));
+        assert_eq!(assemble("arb [rb-2], 1").unwrap_err().message, "Wrong number of operands for arb");
+        assert_eq!(assemble("out [x]").unwrap_err().message, "Unknown symbol x");
+        assert_eq!(assemble("hlt\n2: hlt").unwrap_err(), AsmError { line: 2, message: "Expected address 2, found 1".to_string() });
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for source in &[include_str!("../../day-05/input.txt"), include_str!("../../day-07/input.txt")] {
+            let program: Program = crate::parse(source).unwrap();
+            assert_eq!(round_trip(&program), program);
+        }
+
+        assert_eq!(round_trip(&[1, 0, 0, 0, 20004, -7, 204, -7]), vec![1, 0, 0, 0, 20004, -7, 204, -7]);
+    }
+}
@@ -0,0 +1,21 @@
+use std::process;
+
+use intcode::asm;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let filename = &args[1];
+
+    let source = std::fs::read_to_string(filename).unwrap_or_else(|e| {
+        eprintln!("{}: {}", filename, e);
+        process::exit(1)
+    });
+
+    match asm::assemble(&source) {
+        Ok(program) => println!("{}", intcode::format(&program)),
+        Err(e) => {
+            eprintln!("{}: {}", filename, e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::process;
+
+use intcode::analysis;
+use intcode::Program;
+
+const USAGE: &str = "usage: cfg <program> <dot-file> <json-file>";
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.len() != 3 {
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+
+    let program: Program = intcode::load(&args[0]).unwrap_or_else(|e| {
+        eprintln!("{}: {}", &args[0], e);
+        process::exit(1)
+    });
+    let analysis = analysis::analyze(&program);
+
+    analysis.write_dot(&program, &mut BufWriter::new(File::create(&args[1])?))?;
+    analysis.write_json(&mut BufWriter::new(File::create(&args[2])?))?;
+
+    println!(
+        "{} blocks, {} unresolved jumps, {} writes into code",
+        analysis.blocks.len(),
+        analysis.computed_jumps.values().filter(|target| target.is_none()).count(),
+        analysis.code_writes.len()
+    );
+
+    Ok(())
+}
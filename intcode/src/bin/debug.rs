@@ -0,0 +1,34 @@
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use intcode::debugger::Debugger;
+use intcode::Machine;
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let filename = &args[1];
+
+    let program = intcode::load(filename).unwrap_or_else(|e| {
+        eprintln!("{}: {}", filename, e);
+        process::exit(1)
+    });
+
+    let mut debugger = Debugger::new(Machine::new(program));
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    debugger.command("list", &mut out)?;
+
+    loop {
+        write!(out, "(debug) ")?;
+        out.flush()?;
+
+        let mut line = String::new();
+
+        if stdin.lock().read_line(&mut line)? == 0 || !debugger.command(&line, &mut out)? {
+            return Ok(());
+        }
+    }
+}
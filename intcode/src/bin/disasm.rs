@@ -0,0 +1,27 @@
+use std::process;
+
+use intcode::disasm;
+use intcode::Program;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    let (linear, filename) = match args.get(1).map(|s| s.as_str()) {
+        Some("--linear") => (true, &args[2]),
+        _ => (false, &args[1]),
+    };
+
+    let program: Program = intcode::load(filename).unwrap_or_else(|e| {
+        eprintln!("{}: {}", filename, e);
+        process::exit(1)
+    });
+
+    let items = match linear {
+        true => disasm::disassemble_linear(&program),
+        false => disasm::disassemble(&program),
+    };
+
+    for (addr, item) in items {
+        println!("{:>6}: {}", addr, item);
+    }
+}
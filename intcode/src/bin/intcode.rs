@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use intcode::session::{self, Entry, Session};
+use intcode::{ascii, Machine, Program, Yield};
+
+const USAGE: &str = "\
+usage: intcode run [--ascii] [--script <file>] [--record <file>] <program>
+       intcode replay <session> <program>
+
+  --ascii            read input as lines of text and print output as text
+  --script <file>    take input from <file> before reading stdin
+  --record <file>    save a transcript of the session to <file>
+
+replay feeds the inputs recorded in <session> to <program> and reports
+where its outputs differ from the recording.";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1)
+}
+
+#[derive(Default)]
+struct Options {
+    ascii: bool,
+    script: Option<String>,
+    record: Option<String>,
+    program: Option<String>,
+}
+
+fn parse_options(args: &[String]) -> Options {
+    let mut options = Options::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ascii" => options.ascii = true,
+            "--script" => options.script = Some(args.next().unwrap_or_else(|| usage()).clone()),
+            "--record" => options.record = Some(args.next().unwrap_or_else(|| usage()).clone()),
+            _ if options.program.is_none() && !arg.starts_with("--") => options.program = Some(arg.clone()),
+            _ => usage(),
+        }
+    }
+
+    options
+}
+
+/// Converts a line of input to values: the characters of the line in ASCII
+/// mode, otherwise numbers separated by commas or whitespace.
+fn parse_line(line: &str, ascii: bool) -> Result<Vec<i64>, String> {
+    if ascii {
+        return Ok(ascii::encode(line).collect());
+    }
+
+    line.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().map_err(|_| format!("Invalid number {}", s)))
+        .collect()
+}
+
+/// Reports a failure to read `path` and exits.
+fn or_exit<T>(res: io::Result<T>, path: &str) -> T {
+    res.unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(1)
+    })
+}
+
+/// Runs the machine, taking input from `pending` and then from the lines of
+/// `input`, and writing output to `out` as it is produced. Returns the
+/// transcript, which ends in an error entry if the machine failed.
+fn interact(
+    machine: &mut Machine,
+    mut pending: VecDeque<i64>,
+    ascii: bool,
+    mut input: impl BufRead,
+    out: &mut impl Write,
+) -> io::Result<Session> {
+    let mut session = Session::new();
+
+    loop {
+        let state = match machine.run() {
+            Ok(state) => state,
+            Err(e) => {
+                session.entries.push(Entry::Error(e.to_string()));
+                return Ok(session);
+            }
+        };
+
+        match state {
+            Yield::Halted => {
+                session.entries.push(Entry::Halted);
+                return Ok(session);
+            }
+            Yield::Output(val) => {
+                session.entries.push(Entry::Output(val));
+
+                match ascii::render(&[val]).pop() {
+                    Some(ascii::Output::Text(text)) if ascii => write!(out, "{}", text)?,
+                    _ => writeln!(out, "{}", val)?,
+                }
+
+                out.flush()?;
+            }
+            Yield::NeedsInput => {
+                while pending.is_empty() {
+                    if !ascii {
+                        write!(out, "? ")?;
+                        out.flush()?;
+                    }
+
+                    let mut line = String::new();
+
+                    if input.read_line(&mut line)? == 0 {
+                        break;
+                    }
+
+                    match parse_line(line.trim_end_matches(['\r', '\n']), ascii) {
+                        Ok(values) => pending.extend(values),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+
+                match pending.pop_front() {
+                    Some(val) => {
+                        session.entries.push(Entry::Input(val));
+                        machine.input.push_back(val);
+                    }
+                    None => {
+                        eprintln!("Input ended while the program was waiting for more");
+                        return Ok(session);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn run(options: Options) -> io::Result<()> {
+    let path = options.program.as_ref().unwrap_or_else(|| usage());
+    let program: Program = or_exit(intcode::load(path), path);
+
+    let mut pending = VecDeque::new();
+
+    if let Some(script) = &options.script {
+        for line in or_exit(fs::read_to_string(script), script).lines() {
+            match parse_line(line, options.ascii) {
+                Ok(values) => pending.extend(values),
+                Err(e) => {
+                    eprintln!("{}: {}", script, e);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let session = interact(&mut Machine::new(program), pending, options.ascii, stdin.lock(), &mut stdout.lock())?;
+
+    if let Some(record) = &options.record {
+        session.save(record)?;
+    }
+
+    if let Some(Entry::Error(e)) = session.entries.last() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn replay(args: &[String]) -> io::Result<()> {
+    let (session, program) = match args {
+        [session, program] => (session, program),
+        _ => usage(),
+    };
+
+    let session: Session = or_exit(Session::load(session), session);
+    let program: Program = or_exit(intcode::load(program), program);
+    let (_, divergences) = session.replay(&mut Machine::new(program));
+
+    if divergences.is_empty() {
+        println!("Replayed {} entries, no differences", session.entries.len());
+        return Ok(());
+    }
+
+    session::write_report(&divergences, &mut io::stdout().lock())?;
+    process::exit(1)
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(|s| s.as_str()) {
+        Some("run") => run(parse_options(&args[1..])),
+        Some("replay") => replay(&args[1..]),
+        _ => usage(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::asm::assemble;
+
+    fn interact(program: &str, script: &[i64], ascii: bool, input: &str) -> (String, Vec<Entry>) {
+        let mut machine = Machine::new(assemble(program).unwrap());
+        let pending = script.iter().cloned().collect();
+        let mut out = vec![];
+
+        let session = super::interact(&mut machine, pending, ascii, input.as_bytes(), &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), session.entries)
+    }
+
+    #[test]
+    fn test_numbers() {
+        // adds pairs of numbers until the first of a pair is zero
+        let adder = "
+            loop:
+                in [a]
+                jf [a], end
+                in [b]
+                add [a], [b], [a]
+                out [a]
+                jt 1, loop
+            end:
+                hlt
+            a: .data 0
+            b: .data 0
+        ";
+
+        let (out, entries) = interact(adder, &[1], false, "2\n3, 4\n0\n");
+        assert_eq!(out, "? 3\n? 7\n? ");
+
+        let (i, o) = (Entry::Input, Entry::Output);
+        assert_eq!(entries, vec![i(1), i(2), o(3), i(3), i(4), o(7), i(0), Entry::Halted]);
+
+        // stops when input runs out
+        let (out, entries) = interact(adder, &[], false, "5\n");
+        assert_eq!(out, "? ? ");
+        assert_eq!(entries, vec![Entry::Input(5)]);
+    }
+
+    #[test]
+    fn test_ascii() {
+        // counts the characters of a line up to its newline, as a digit
+        let count = "
+            loop:
+                in [c]
+                add [n], 1, [n]
+                eq [c], 10, [t]
+                jf [t], loop
+                out [n]
+                out 10
+                hlt
+            c: .data 0
+            n: .data 48
+            t: .data 0
+        ";
+
+        // no prompts, and the carriage return is dropped with the line ending
+        let (out, entries) = interact(count, &[], true, "hi\r\n");
+        assert_eq!(out, "3\n");
+
+        let (i, o) = (Entry::Input, Entry::Output);
+        assert_eq!(entries, vec![i(104), i(105), i(10), o(51), o(10), Entry::Halted]);
+    }
+
+    #[test]
+    fn test_error() {
+        let (_, entries) = interact("in [1]\n.data 42", &[5], false, "");
+        assert_eq!(entries, vec![Entry::Input(5), Entry::Error("Unknown instruction 42 at 2 [42]".to_string())]);
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::iter;
+use std::process;
+
+use intcode::profile::Profile;
+use intcode::{Machine, Program};
+
+const USAGE: &str = "usage: profile [--folded <file>] <program> [input...]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1)
+}
+
+fn main() -> io::Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    let folded = match args.iter().position(|arg| arg == "--folded") {
+        Some(ii) if ii + 1 < args.len() => {
+            let path = args.remove(ii + 1);
+            args.remove(ii);
+            Some(path)
+        }
+        Some(_) => usage(),
+        None => None,
+    };
+
+    if args.is_empty() {
+        usage();
+    }
+
+    let program: Program = intcode::load(&args[0]).unwrap_or_else(|e| {
+        eprintln!("{}: {}", &args[0], e);
+        process::exit(1)
+    });
+
+    let mut machine = Machine::new(program);
+    machine.input.extend(args[1..].iter().map(|s| s.parse::<i64>().unwrap_or_else(|_| usage())));
+    machine.profile = Some(Profile::new());
+
+    let mut failed = false;
+
+    for res in machine.try_execute(iter::empty()) {
+        match res {
+            Ok(output) => println!("--> {}", output),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                failed = true;
+            }
+        }
+    }
+
+    // The profile up to a fault is still reported.
+    let profile = machine.profile.take().unwrap();
+    profile.write_report(io::stdout(), 10)?;
+
+    if let Some(path) = folded {
+        profile.write_folded(BufWriter::new(File::create(path)?))?;
+    }
+
+    if failed {
+        process::exit(1);
+    }
+
+    Ok(())
+}
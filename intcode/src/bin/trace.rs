@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::iter;
+use std::process;
+
+use intcode::trace::{self, BinaryTracer, TextTracer};
+use intcode::{Machine, Program};
+
+const USAGE: &str = "\
+usage: trace [--binary] <program> <trace-file> [input...]
+       trace --dump <binary-trace-file>";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1)
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let flag = |name| args.iter().any(|arg| arg == name);
+    let positional: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+
+    if flag("--dump") && !positional.is_empty() {
+        for event in trace::read_binary(BufReader::new(File::open(positional[0])?))? {
+            println!("{}", trace::format_event(&event));
+        }
+
+        return Ok(());
+    }
+
+    if positional.len() < 2 {
+        usage();
+    }
+
+    let input: Vec<i64> = positional[2..].iter().map(|s| s.parse().unwrap_or_else(|_| usage())).collect();
+
+    let program: Program = intcode::load(positional[0]).unwrap_or_else(|e| {
+        eprintln!("{}: {}", positional[0], e);
+        process::exit(1)
+    });
+    let writer = BufWriter::new(File::create(positional[1])?);
+
+    let mut machine = Machine::new(program);
+    machine.input.extend(input);
+    machine.tracing = true;
+    machine.tracer = match flag("--binary") {
+        true => Some(Box::new(BinaryTracer::new(writer))),
+        false => Some(Box::new(TextTracer::new(writer))),
+    };
+
+    let mut failed = false;
+
+    for res in machine.try_execute(iter::empty()) {
+        match res {
+            Ok(output) => println!("--> {}", output),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                failed = true;
+            }
+        }
+    }
+
+    // The trace up to a fault is still written out.
+    machine.tracer.take().unwrap().finish()?;
+
+    if failed {
+        process::exit(1);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
+
+use crate::{IntcodeError, Machine, Word, Yield};
+
+/// A machine that reads input from and writes output to channels, so that
+/// any number of machines can be wired together and driven by an executor.
+pub struct AsyncMachine<W = i64> {
+    pub machine: Machine<W>,
+    pub input: UnboundedReceiver<W>,
+    pub output: UnboundedSender<W>,
+}
+
+impl<W: Word> AsyncMachine<W> {
+    pub fn new(machine: Machine<W>, input: UnboundedReceiver<W>, output: UnboundedSender<W>) -> Self {
+        AsyncMachine { machine, input, output }
+    }
+
+    /// Runs the machine, waiting on the input channel whenever the input
+    /// queue is empty.
+    ///
+    /// Completes when the machine halts, or when it needs input and the
+    /// input channel is closed; check `halted` on the returned machine to
+    /// tell the two apart. The output channel is closed on completion.
+    /// Outputs sent after the receiving end was dropped are discarded.
+    pub async fn run(mut self) -> Result<Machine<W>, IntcodeError<W>> {
+        loop {
+            match self.machine.run()? {
+                Yield::Halted => return Ok(self.machine),
+                Yield::Output(val) => {
+                    let _ = self.output.unbounded_send(val);
+                }
+                Yield::NeedsInput => match self.input.next().await {
+                    Some(val) => self.machine.input.push_back(val),
+                    None => return Ok(self.machine),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures::executor::block_on;
+    use futures::future::join_all;
+
+    #[test]
+    fn test_feedback_loop() {
+        let program = crate::parse(
+            "3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,\
+             1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10",
+        )
+        .unwrap();
+
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..5).map(|_| mpsc::unbounded()).unzip();
+
+        for (sender, phase) in senders.iter().zip(vec![9, 7, 8, 5, 6]) {
+            sender.unbounded_send(phase).unwrap();
+        }
+
+        senders[0].unbounded_send(0).unwrap();
+
+        // the last amplifier feeds back into the first one through `tap`
+        let (tap, mut tapped) = mpsc::unbounded();
+        let outputs = senders.iter().skip(1).cloned().chain(Some(tap));
+
+        let amps = receivers
+            .into_iter()
+            .zip(outputs)
+            .map(|(input, output)| AsyncMachine::new(Machine::new(program.clone()), input, output).run());
+
+        let feedback = async {
+            let mut last = None;
+
+            while let Some(val) = tapped.next().await {
+                last = Some(val);
+                let _ = senders[0].unbounded_send(val);
+            }
+
+            last
+        };
+
+        let (machines, last) = block_on(futures::future::join(join_all(amps), feedback));
+        assert!(machines.into_iter().all(|machine| machine.unwrap().halted));
+        assert_eq!(last, Some(18216));
+    }
+
+    #[test]
+    fn test_closed_channels() {
+        let doubler = crate::asm::assemble(
+            "
+            loop:
+                in [x]
+                mul [x], 2, [x]
+                out [x]
+                jt 1, loop
+            x: .data 0
+        ",
+        )
+        .unwrap();
+
+        // stops without halting once the input is closed, then closes its output
+        let (sender, input) = mpsc::unbounded();
+        let (output, receiver) = mpsc::unbounded();
+        sender.unbounded_send(1).unwrap();
+        sender.unbounded_send(2).unwrap();
+        drop(sender);
+
+        let machine = block_on(AsyncMachine::new(Machine::new(doubler.clone()), input, output).run()).unwrap();
+        assert!(!machine.halted);
+        assert_eq!(block_on(receiver.collect::<Vec<i64>>()), vec![2, 4]);
+
+        // keeps running after the receiving end of its output is dropped
+        let (sender, input) = mpsc::unbounded();
+        let (output, receiver) = mpsc::unbounded();
+        drop(receiver);
+        sender.unbounded_send(3).unwrap();
+        drop(sender);
+
+        let machine = block_on(AsyncMachine::new(Machine::new(doubler.clone()), input, output).run()).unwrap();
+        assert_eq!(machine.memory.get(doubler.len() - 1), 6);
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+
+use crate::{IntcodeError, Instruction, Machine, Memory, Mode, Word};
+
+/// A parameter with its addressing resolved as far as possible at compile
+/// time.
+enum Operand<W> {
+    Value(W),
+    Position(usize),
+    Relative(W),
+}
+
+impl<W: Word> Operand<W> {
+    /// Returns `None` for position parameters that are not valid addresses,
+    /// leaving the error to the interpreter.
+    fn new(mode: Mode, param: W) -> Option<Self> {
+        match mode {
+            Mode::Value => Some(Operand::Value(param)),
+            Mode::Position => match param.to_i64() {
+                Some(addr) if addr >= 0 => Some(Operand::Position(addr as usize)),
+                _ => None,
+            },
+            Mode::Relative => Some(Operand::Relative(param)),
+        }
+    }
+
+    fn load(&self, machine: &Machine<W>) -> Result<W, IntcodeError<W>> {
+        match self {
+            Operand::Value(val) => Ok(val.clone()),
+            Operand::Position(addr) => Ok(machine.memory.get(*addr)),
+            Operand::Relative(offset) => machine.get(Mode::Relative, offset),
+        }
+    }
+
+    fn addr(&self, machine: &Machine<W>) -> Result<usize, IntcodeError<W>> {
+        match self {
+            Operand::Value(_) => machine.addr(Mode::Value, &W::from_i64(0)),
+            Operand::Position(addr) => Ok(*addr),
+            Operand::Relative(offset) => machine.addr(Mode::Relative, offset),
+        }
+    }
+}
+
+/// Executes one instruction, returning the address written, if any.
+type Run<W> = Box<dyn Fn(&mut Machine<W>) -> Result<Option<usize>, IntcodeError<W>> + Send + Sync>;
+
+/// Computes the result of a three-operand instruction.
+type Binary<W> = fn(&Machine<W>, W, W) -> Result<W, IntcodeError<W>>;
+
+struct Op<W> {
+    pc: usize,
+    next: usize,
+    run: Run<W>,
+}
+
+/// Straight-line code compiled into closures. The block ends before the
+/// first instruction that jumps, does I/O, halts, or cannot be compiled;
+/// that instruction is left to the interpreter.
+pub(crate) struct Block<W> {
+    ops: Vec<Op<W>>,
+    end: usize,
+}
+
+impl<W> fmt::Debug for Block<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Block({} ops, end {})", self.ops.len(), self.end)
+    }
+}
+
+/// Compiles the instruction at `pc`, returning it along with its size.
+fn compile_op<W: Word>(memory: &Memory<W>, pc: usize) -> Option<(Run<W>, usize)> {
+    let instruction = Instruction::parse(memory.get(pc).to_i64()?).ok()?;
+
+    if !memory.is_original(pc, instruction.size()) {
+        return None;
+    }
+
+    let operand = |ii: usize, mode: Mode| Operand::new(mode, memory.get(pc + 1 + ii));
+
+    let binary = |mode0, mode1, mode2, f: Binary<W>| -> Option<Run<W>> {
+        let (a, b, c) = (operand(0, mode0)?, operand(1, mode1)?, operand(2, mode2)?);
+
+        Some(Box::new(move |machine: &mut Machine<W>| {
+            let (a, b) = (a.load(machine)?, b.load(machine)?);
+            let addr = c.addr(machine)?;
+            machine.write(addr, f(machine, a, b)?)?;
+            Ok(Some(addr))
+        }))
+    };
+
+    let run = match instruction {
+        Instruction::Add(mode0, mode1, mode2) => binary(mode0, mode1, mode2, |m, a, b| m.add(a, b)),
+        Instruction::Multiply(mode0, mode1, mode2) => binary(mode0, mode1, mode2, |m, a, b| m.mul(a, b)),
+        Instruction::LessThan(mode0, mode1, mode2) => {
+            binary(mode0, mode1, mode2, |_, a, b| Ok(W::from_i64(if a < b { 1 } else { 0 })))
+        }
+        Instruction::Equals(mode0, mode1, mode2) => {
+            binary(mode0, mode1, mode2, |_, a, b| Ok(W::from_i64(if a == b { 1 } else { 0 })))
+        }
+        Instruction::AdjustBase(mode0) => {
+            let a = operand(0, mode0)?;
+
+            let run: Run<W> = Box::new(move |machine: &mut Machine<W>| {
+                let offset = a.load(machine)?;
+                machine.relative_base = machine.relative(&offset)?;
+                Ok(None)
+            });
+
+            Some(run)
+        }
+        _ => None,
+    }?;
+
+    Some((run, instruction.size()))
+}
+
+fn compile<W: Word>(memory: &Memory<W>, start: usize) -> Block<W> {
+    let mut ops = vec![];
+    let mut pc = start;
+
+    while let Some((run, size)) = compile_op(memory, pc) {
+        ops.push(Op { pc, next: pc + size, run });
+        pc += size;
+    }
+
+    Block { ops, end: pc }
+}
+
+impl<W: Word> Machine<W> {
+    /// Runs the block compiled at `pc`, compiling it first if needed. Does
+    /// nothing if `pc` lies outside the cached region, or if the block's code
+    /// has been written since it was compiled.
+    pub(crate) fn run_block(&mut self) -> Result<(), IntcodeError<W>> {
+        self.memory.prepare_cache();
+
+        if !self.memory.is_original(self.pc, 1) {
+            return Ok(());
+        }
+
+        let block = match self.memory.block(self.pc) {
+            Some(block) => block,
+            None => {
+                let block = compile(&self.memory, self.pc);
+                self.memory.cache_block(self.pc, block)
+            }
+        };
+
+        if block.ops.iter().any(|op| self.memory.is_written(op.pc)) {
+            return Ok(());
+        }
+
+        for op in &block.ops {
+            self.pc = op.pc;
+            let written = (op.run)(self)?;
+            self.pc = op.next;
+
+            // the rest of the block was compiled from code that just changed
+            if let Some(addr) = written {
+                if addr >= op.next && addr < block.end {
+                    return Ok(());
+                }
+            }
+        }
+
+        self.pc = block.end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, Program};
+    use std::iter;
+
+    fn run(program: &Program, input: i64, backend: Backend) -> Vec<i64> {
+        let mut machine = Machine::new(program.clone());
+        machine.backend = backend;
+        machine.execute(iter::once(input)).collect()
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let program: Program = crate::parse(include_str!("../../day-05/input.txt")).unwrap();
+
+        for input in [1, 5] {
+            assert_eq!(run(&program, input, Backend::Compiled), run(&program, input, Backend::Interpreter));
+        }
+
+        // the destination is checked before the result is computed
+        let error = |backend| {
+            let mut machine = Machine::new(vec![11102, i64::MAX, 2, 0, 99]);
+            machine.backend = backend;
+            let error = machine.try_execute(iter::empty()).find_map(Result::err);
+            error
+        };
+
+        assert!(matches!(error(Backend::Compiled), Some(IntcodeError::ValueModeWrite { .. })));
+        assert_eq!(error(Backend::Compiled), error(Backend::Interpreter));
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // the first add rewrites an operand of the second one
+        let program = vec![1101, 5, 0, 6, 1101, 0, 0, 100, 4, 100, 99];
+
+        let mut prototype = Machine::new(program);
+        prototype.backend = Backend::Compiled;
+        prototype.memory.prepare_cache();
+
+        for _ in 0..2 {
+            let output: Vec<i64> = prototype.clone().execute(iter::empty()).collect();
+            assert_eq!(output, vec![5]);
+        }
+    }
+
+    #[test]
+    fn test_outside_image() {
+        // writes a halt past the end of the program and jumps to it
+        let mut machine = Machine::new(vec![1101, 0, 99, 10, 1105, 1, 10]);
+        machine.backend = Backend::Compiled;
+
+        assert_eq!(machine.run(), Ok(crate::Yield::Halted));
+        assert_eq!(machine.pc, 10);
+    }
+}
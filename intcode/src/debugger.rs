@@ -0,0 +1,345 @@
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+use crate::disasm::{self, Item};
+use crate::{Bisection, History, Machine, Snapshot, State};
+
+const HELP: &str = "\
+step [n]          execute n instructions (default 1)
+continue          run until a breakpoint, watchpoint, input request or halt
+back [n]          undo n instructions (default 1)
+lastwrite <addr>  go back to just before the last write to addr
+bisect <v>        go back to just before the first output other than v
+break <addr>      set a breakpoint on pc
+delete <addr>     remove a breakpoint
+watch <addr>      stop whenever the value at addr changes
+unwatch <addr>    remove a watchpoint
+input <v>...      queue input values
+regs              show pc, relative base and pending input
+x <addr> [n]      dump n words of memory (default 8)
+poke <addr> <v>   write v to addr, clearing the history
+list [addr] [n]   disassemble n instructions from addr (default pc, 5)
+save <file>       save a snapshot of the machine
+load <file>       restore the machine from a snapshot
+quit              exit the debugger";
+
+/// The most instructions the debugger can step back over.
+const HISTORY_LIMIT: usize = 1_000_000;
+
+/// Why `continue` stopped.
+enum Stop {
+    Breakpoint,
+    Watchpoint(usize, i64, i64),
+    State(State),
+}
+
+pub struct Debugger {
+    pub machine: Machine,
+    pub breakpoints: BTreeSet<usize>,
+    pub watchpoints: BTreeSet<usize>,
+}
+
+impl Debugger {
+    /// Starts recording the machine's history so that it can be stepped
+    /// backwards, keeping the last million instructions unless the machine
+    /// already has a history.
+    pub fn new(mut machine: Machine) -> Self {
+        if machine.history.is_none() {
+            machine.history = Some(History::with_limit(HISTORY_LIMIT));
+        }
+
+        Debugger {
+            machine,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Disassembles the instruction at `addr`, or shows the word as data if
+    /// it does not decode.
+    pub fn describe(&self, addr: usize) -> (usize, Item) {
+        let words: Vec<i64> = (addr..addr + 4).map(|a| self.machine.memory.get(a)).collect();
+
+        let item = match disasm::decode(&words, 0) {
+            Some(item) => item,
+            None => Item::Data(vec![words[0]]),
+        };
+
+        (item.size(), item)
+    }
+
+    fn print_current(&self, out: &mut impl Write) -> io::Result<()> {
+        let (_, item) = self.describe(self.machine.pc);
+        writeln!(out, "{:>6}: {}", self.machine.pc, item)
+    }
+
+    /// Executes one instruction, reporting outputs and watched writes.
+    fn step(&mut self, out: &mut impl Write) -> io::Result<Option<Stop>> {
+        let watched: Vec<_> = self.watchpoints.iter().map(|addr| (*addr, self.machine.memory.get(*addr))).collect();
+
+        let state = match self.machine.step() {
+            Ok(state) => state,
+            Err(e) => {
+                writeln!(out, "Error: {}", e)?;
+                return Ok(Some(Stop::State(State::Halted)));
+            }
+        };
+
+        if let State::Output(val) = &state {
+            writeln!(out, "--> {}", val)?;
+        }
+
+        for (addr, old) in watched {
+            let new = self.machine.memory.get(addr);
+
+            if new != old {
+                return Ok(Some(Stop::Watchpoint(addr, old, new)));
+            }
+        }
+
+        match state {
+            State::Running | State::Output(_) => Ok(None),
+            state => Ok(Some(Stop::State(state))),
+        }
+    }
+
+    fn report(&self, stop: Stop, out: &mut impl Write) -> io::Result<()> {
+        match stop {
+            Stop::Breakpoint => writeln!(out, "Breakpoint at {}", self.machine.pc)?,
+            Stop::Watchpoint(addr, old, new) => writeln!(out, "Watchpoint {}: {} -> {}", addr, old, new)?,
+            Stop::State(State::NeedsInput) => writeln!(out, "Waiting for input")?,
+            Stop::State(State::Halted) if self.machine.halted => writeln!(out, "Halted")?,
+            Stop::State(_) => {}
+        }
+
+        self.print_current(out)
+    }
+
+    fn run(&mut self, count: Option<usize>, out: &mut impl Write) -> io::Result<()> {
+        let mut executed = 0;
+
+        loop {
+            if let Some(stop) = self.step(out)? {
+                return self.report(stop, out);
+            }
+
+            executed += 1;
+
+            if count == Some(executed) {
+                return self.print_current(out);
+            }
+
+            if count.is_none() && self.breakpoints.contains(&self.machine.pc) {
+                return self.report(Stop::Breakpoint, out);
+            }
+        }
+    }
+
+    /// Runs a single debugger command. Returns `false` once the user quits.
+    pub fn command(&mut self, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["save", path] => {
+                if let Err(e) = self.machine.snapshot().save(path) {
+                    writeln!(out, "Cannot save snapshot: {}", e)?;
+                }
+                return Ok(true);
+            }
+            ["load", path] => {
+                match Snapshot::load(path) {
+                    Ok(snapshot) => {
+                        self.machine.restore(snapshot);
+                        self.print_current(out)?;
+                    }
+                    Err(e) => writeln!(out, "Cannot load snapshot: {}", e)?,
+                }
+                return Ok(true);
+            }
+            _ => {}
+        }
+
+        let args: Result<Vec<i64>, _> = words.iter().skip(1).map(|s| s.parse::<i64>()).collect();
+
+        let args = match args {
+            Ok(args) => args,
+            Err(_) => {
+                writeln!(out, "Invalid arguments")?;
+                return Ok(true);
+            }
+        };
+
+        let addr = |ii: usize| args.get(ii).filter(|x| **x >= 0).map(|x| *x as usize);
+
+        match (words.first().copied(), args.len()) {
+            (None, _) => {}
+            (Some("q"), 0) | (Some("quit"), 0) => return Ok(false),
+            (Some("h"), 0) | (Some("help"), 0) => writeln!(out, "{}", HELP)?,
+            (Some("s"), n) | (Some("step"), n) if n <= 1 => match args.first() {
+                Some(x) if *x <= 0 => writeln!(out, "Invalid count")?,
+                x => self.run(Some(x.copied().unwrap_or(1) as usize), out)?,
+            },
+            (Some("c"), 0) | (Some("continue"), 0) => self.run(None, out)?,
+            (Some("back"), n) if n <= 1 => {
+                for _ in 0..args.first().copied().unwrap_or(1) {
+                    if !self.machine.step_back() {
+                        writeln!(out, "At start of history")?;
+                        break;
+                    }
+                }
+
+                self.print_current(out)?;
+            }
+            (Some("lastwrite"), 1) if addr(0).is_some() => {
+                if !self.machine.last_write(addr(0).unwrap()) {
+                    writeln!(out, "No write to {} in history", addr(0).unwrap())?;
+                }
+
+                self.print_current(out)?;
+            }
+            (Some("bisect"), 1) => {
+                let expected = args[0];
+                let bad = |m: &Machine| m.history.as_ref().unwrap().outputs().any(|val| *val != expected);
+
+                match self.machine.bisect(bad) {
+                    Ok(Bisection::BadAfter(position)) => writeln!(out, "First bad output after {} instructions", position)?,
+                    Ok(Bisection::BadFromStart) => writeln!(out, "Output other than {} before the start of history", expected)?,
+                    Ok(Bisection::Good) => writeln!(out, "No output other than {} so far", expected)?,
+                    Err(e) => writeln!(out, "Error: {}", e)?,
+                }
+
+                self.print_current(out)?;
+            }
+            (Some("b"), 1) | (Some("break"), 1) if addr(0).is_some() => {
+                self.breakpoints.insert(addr(0).unwrap());
+            }
+            (Some("d"), 1) | (Some("delete"), 1) if addr(0).is_some() => {
+                self.breakpoints.remove(&addr(0).unwrap());
+            }
+            (Some("w"), 1) | (Some("watch"), 1) if addr(0).is_some() => {
+                self.watchpoints.insert(addr(0).unwrap());
+            }
+            (Some("unwatch"), 1) if addr(0).is_some() => {
+                self.watchpoints.remove(&addr(0).unwrap());
+            }
+            (Some("i"), _) | (Some("input"), _) => self.machine.input.extend(args),
+            (Some("r"), 0) | (Some("regs"), 0) => {
+                writeln!(out, "pc = {}", self.machine.pc)?;
+                writeln!(out, "rb = {}", self.machine.relative_base)?;
+                writeln!(out, "input = {:?}", self.machine.input)?;
+                writeln!(out, "halted = {}", self.machine.halted)?;
+            }
+            (Some("x"), 1) | (Some("x"), 2) if addr(0).is_some() => {
+                let start = addr(0).unwrap();
+                let count = addr(1).unwrap_or(8);
+
+                for row in (start..start + count).step_by(8) {
+                    let end = usize::min(row + 8, start + count);
+                    let values: Vec<_> = (row..end).map(|a| self.machine.memory.get(a).to_string()).collect();
+                    writeln!(out, "{:>6}: {}", row, values.join(" "))?;
+                }
+            }
+            (Some("poke"), 2) if addr(0).is_some() => match self.machine.memory.set(addr(0).unwrap(), args[1]) {
+                Ok(()) => {
+                    // Replaying the history would not repeat the poke, so it is discarded.
+                    if let Some(history) = &mut self.machine.history {
+                        history.changes.clear();
+                    }
+                }
+                Err(e) => writeln!(out, "{}", e)?,
+            },
+            (Some("l"), n) | (Some("list"), n) if n <= 2 => {
+                let mut at = addr(0).unwrap_or(self.machine.pc);
+
+                for _ in 0..addr(1).unwrap_or(5) {
+                    let (size, item) = self.describe(at);
+                    let marker = if at == self.machine.pc { ">" } else { " " };
+                    writeln!(out, "{}{:>5}: {}", marker, at, item)?;
+                    at += size;
+                }
+            }
+            _ => writeln!(out, "Unknown command; try 'help'")?,
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts from 1 to 3 in [14], outputting each value.
+    fn debugger() -> Debugger {
+        let program = crate::asm::assemble(
+            "
+            loop:
+                add [x], 1, [x]
+                out [x]
+                lt [x], 3, [t]
+                jt [t], loop
+                hlt
+            x: .data 0
+            t: .data 0
+        ",
+        )
+        .unwrap();
+
+        Debugger::new(Machine::new(program))
+    }
+
+    fn command(debugger: &mut Debugger, line: &str) -> String {
+        let mut out = vec![];
+        debugger.command(line, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let mut debugger = debugger();
+
+        assert_eq!(command(&mut debugger, "break 6"), "");
+        assert_eq!(command(&mut debugger, "continue"), "--> 1\nBreakpoint at 6\n     6: lt [14], 3, [15]\n");
+        assert_eq!(command(&mut debugger, "continue"), "--> 2\nBreakpoint at 6\n     6: lt [14], 3, [15]\n");
+
+        command(&mut debugger, "delete 6");
+        assert_eq!(command(&mut debugger, "continue"), "--> 3\nHalted\n    13: hlt\n");
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        let mut debugger = debugger();
+
+        command(&mut debugger, "watch 14");
+        assert_eq!(command(&mut debugger, "continue"), "Watchpoint 14: 0 -> 1\n     4: out [14]\n");
+        assert_eq!(command(&mut debugger, "continue"), "--> 1\nWatchpoint 14: 1 -> 2\n     4: out [14]\n");
+    }
+
+    #[test]
+    fn test_back() {
+        let mut debugger = debugger();
+        command(&mut debugger, "continue");
+
+        assert_eq!(command(&mut debugger, "back 2"), "    10: jt [15], 0\n");
+        assert_eq!(command(&mut debugger, "lastwrite 14"), "     0: add [14], 1, [14]\n");
+        assert_eq!(debugger.machine.memory.get(14), 2);
+
+        assert_eq!(command(&mut debugger, "back 100"), "At start of history\n     0: add [14], 1, [14]\n");
+        assert_eq!(debugger.machine.memory.get(14), 0);
+    }
+
+    #[test]
+    fn test_memory() {
+        let mut debugger = debugger();
+
+        assert_eq!(command(&mut debugger, "poke 14 42"), "");
+        assert_eq!(command(&mut debugger, "x 13 3"), "    13: 99 42 0\n");
+        assert_eq!(command(&mut debugger, "x 0 10"), "     0: 1001 14 1 14 4 14 1007 14\n     8: 3 15\n");
+        assert_eq!(command(&mut debugger, "poke -1 0"), "Unknown command; try 'help'\n");
+
+        command(&mut debugger, "step 2");
+        command(&mut debugger, "poke 14 7");
+        assert_eq!(command(&mut debugger, "back"), "At start of history\n     6: lt [14], 3, [15]\n");
+        assert_eq!(debugger.machine.memory.get(14), 7);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::{Instruction, Mode};
+
+/// A parameter together with the mode it is interpreted in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Operand {
+    pub mode: Mode,
+    pub value: i64,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            Mode::Position => write!(f, "[{}]", self.value),
+            Mode::Value => write!(f, "{}", self.value),
+            Mode::Relative if self.value < 0 => write!(f, "[rb{}]", self.value),
+            Mode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Code(Instruction, Vec<Operand>),
+    Data(Vec<i64>),
+}
+
+impl Item {
+    pub fn size(&self) -> usize {
+        match self {
+            Item::Code(instruction, _) => instruction.size(),
+            Item::Data(words) => words.len(),
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Item::Code(instruction, operands) => {
+                write!(f, "{}", instruction.mnemonic())?;
+
+                for (ii, operand) in operands.iter().enumerate() {
+                    write!(f, "{}{}", if ii == 0 { " " } else { ", " }, operand)?;
+                }
+
+                Ok(())
+            }
+            Item::Data(words) => {
+                write!(f, ".data")?;
+
+                for (ii, word) in words.iter().enumerate() {
+                    write!(f, "{}{}", if ii == 0 { " " } else { ", " }, word)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Decodes the instruction at `addr`, provided it is in canonical form and
+/// fits in the program.
+pub fn decode(program: &[i64], addr: usize) -> Option<Item> {
+    let raw = *program.get(addr)?;
+    let instruction = Instruction::parse(raw).ok()?;
+
+    if instruction.encode() != raw || addr + instruction.size() > program.len() {
+        return None;
+    }
+
+    let operands = instruction
+        .modes()
+        .into_iter()
+        .enumerate()
+        .map(|(ii, mode)| Operand { mode, value: program[addr + 1 + ii] })
+        .collect();
+
+    Some(Item::Code(instruction, operands))
+}
+
+/// Addresses following the instruction at `addr` in control flow, as far as
+/// they can be determined statically.
+pub fn successors(addr: usize, instruction: &Instruction, operands: &[Operand]) -> Vec<usize> {
+    let next = addr + instruction.size();
+
+    match instruction {
+        Instruction::Halt => vec![],
+        Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) => {
+            let jumps_if_true = matches!(instruction, Instruction::JumpIfTrue(..));
+
+            let (cond, target) = (operands[0], operands[1]);
+            let mut res = vec![];
+
+            match cond.mode {
+                Mode::Value if (cond.value != 0) == jumps_if_true => {}
+                _ => res.push(next),
+            }
+
+            match cond.mode {
+                Mode::Value if (cond.value != 0) != jumps_if_true => {}
+                _ if target.mode == Mode::Value && target.value >= 0 => res.push(target.value as usize),
+                _ => {}
+            }
+
+            res
+        }
+        _ => vec![next],
+    }
+}
+
+/// Start addresses of all instructions reachable from address 0 by
+/// following fall-through and statically known jump targets.
+pub fn reachable(program: &[i64]) -> BTreeSet<usize> {
+    let mut visited = BTreeSet::new();
+    let mut pending = vec![0];
+
+    while let Some(addr) = pending.pop() {
+        if visited.contains(&addr) {
+            continue;
+        }
+
+        if let Some(Item::Code(instruction, operands)) = decode(program, addr) {
+            visited.insert(addr);
+            pending.extend(successors(addr, &instruction, &operands));
+        }
+    }
+
+    visited
+}
+
+/// Splits the program into instructions and data. Only words reachable as
+/// code are decoded; everything else is reported as data.
+pub fn disassemble(program: &[i64]) -> Vec<(usize, Item)> {
+    let code = reachable(program);
+    split(program, |addr| code.contains(&addr))
+}
+
+/// Like `disassemble`, but decodes every word it can, without following
+/// control flow. Useful for self-modifying programs.
+pub fn disassemble_linear(program: &[i64]) -> Vec<(usize, Item)> {
+    split(program, |_| true)
+}
+
+/// Consecutive data words are grouped into runs of at most 8.
+fn split(program: &[i64], is_code: impl Fn(usize) -> bool) -> Vec<(usize, Item)> {
+    let mut items: Vec<(usize, Item)> = vec![];
+    let mut addr = 0;
+
+    while addr < program.len() {
+        let item = match decode(program, addr) {
+            Some(item) if is_code(addr) => item,
+            _ => match items.last_mut() {
+                Some((_, Item::Data(words))) if words.len() < 8 => {
+                    words.push(program[addr]);
+                    addr += 1;
+                    continue;
+                }
+                _ => Item::Data(vec![program[addr]]),
+            },
+        };
+
+        let size = item.size();
+        items.push((addr, item));
+        addr += size;
+    }
+
+    items
+}
@@ -0,0 +1,86 @@
+use std::fmt::{self, Debug, Display};
+
+/// The instruction that was executing when an error occurred.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fault<W = i64> {
+    pub pc: usize,
+    /// The raw words of the instruction, starting with the opcode.
+    pub words: Vec<W>,
+}
+
+impl<W> Fault<W> {
+    pub fn opcode(&self) -> &W {
+        &self.words[0]
+    }
+}
+
+impl<W: Display> Display for Fault<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at {} [", self.pc)?;
+
+        for (ii, word) in self.words.iter().enumerate() {
+            if ii > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", word)?;
+        }
+
+        write!(f, "]")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IntcodeError<W = i64> {
+    UnknownOpcode { fault: Fault<W> },
+    UnknownMode { fault: Fault<W>, mode: i64 },
+    NegativeAddress { fault: Fault<W>, addr: i64 },
+    /// A word used as an opcode, address or offset does not fit in an `i64`.
+    ValueOutOfRange { fault: Fault<W>, value: W },
+    ValueModeWrite { fault: Fault<W> },
+    MemoryLimitExceeded { fault: Fault<W>, addr: usize, limit: usize },
+    Overflow { fault: Fault<W>, a: W, b: W },
+    /// An extension instruction's handler failed.
+    Extension { fault: Fault<W>, message: String },
+}
+
+impl<W> IntcodeError<W> {
+    pub fn fault(&self) -> &Fault<W> {
+        use IntcodeError::*;
+
+        match self {
+            UnknownOpcode { fault }
+            | UnknownMode { fault, .. }
+            | NegativeAddress { fault, .. }
+            | ValueOutOfRange { fault, .. }
+            | ValueModeWrite { fault }
+            | MemoryLimitExceeded { fault, .. }
+            | Overflow { fault, .. }
+            | Extension { fault, .. } => fault,
+        }
+    }
+
+    pub fn pc(&self) -> usize {
+        self.fault().pc
+    }
+}
+
+impl<W: Display> Display for IntcodeError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use IntcodeError::*;
+
+        match self {
+            UnknownOpcode { fault } => write!(f, "Unknown instruction {} {}", fault.opcode(), fault),
+            UnknownMode { fault, mode } => write!(f, "Unknown mode {} {}", mode, fault),
+            NegativeAddress { fault, addr } => write!(f, "Negative position {} {}", addr, fault),
+            ValueOutOfRange { fault, value } => write!(f, "Value {} out of range {}", value, fault),
+            ValueModeWrite { fault } => write!(f, "Write to parameter in value mode {}", fault),
+            MemoryLimitExceeded { fault, addr, limit } => {
+                write!(f, "Address {} exceeds memory limit {} {}", addr, limit, fault)
+            }
+            Overflow { fault, a, b } => write!(f, "Overflow on operands {} and {} {}", a, b, fault),
+            Extension { fault, message } => write!(f, "{} {}", message, fault),
+        }
+    }
+}
+
+impl<W: Debug + Display> std::error::Error for IntcodeError<W> {}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::trace::{Event, Operation};
+use crate::{Change, Instruction, IntcodeError, Machine, Mode, State, Word};
+
+/// How an extension instruction uses one of its parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Param {
+    /// Read in any mode, and passed to the handler.
+    Read,
+    /// Written with one of the handler's results; value mode is an error.
+    Write,
+}
+
+/// Computes the values of the `Write` parameters from those of the `Read`
+/// parameters, both in order. An `Err` stops the machine.
+pub type Handler<W> = Arc<dyn Fn(&[W]) -> Result<Vec<W>, String> + Send + Sync>;
+
+/// An instruction added to the machine at runtime.
+#[derive(Clone)]
+pub struct Extension<W = i64> {
+    pub mnemonic: String,
+    pub params: Vec<Param>,
+    pub handler: Handler<W>,
+}
+
+impl<W> fmt::Debug for Extension<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Extension({} {:?})", self.mnemonic, self.params)
+    }
+}
+
+impl<W> Extension<W> {
+    pub fn new(
+        mnemonic: &str,
+        params: &[Param],
+        handler: impl Fn(&[W]) -> Result<Vec<W>, String> + Send + Sync + 'static,
+    ) -> Self {
+        Extension {
+            mnemonic: mnemonic.to_string(),
+            params: params.to_vec(),
+            handler: Arc::new(handler),
+        }
+    }
+
+    /// Number of words occupied by the instruction, including the opcode.
+    pub fn size(&self) -> usize {
+        self.params.len() + 1
+    }
+}
+
+/// An instruction word has room for the opcode and 17 mode digits.
+pub const MAX_PARAMS: usize = 17;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterError {
+    /// Opcodes are the last two digits of an instruction word.
+    OutOfRange(i64),
+    Builtin(i64),
+    Taken(i64),
+    /// More parameters than an instruction word has mode digits for.
+    TooManyParams(usize),
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegisterError::OutOfRange(x) => write!(f, "Opcode {} is not between 1 and 99", x),
+            RegisterError::Builtin(x) => write!(f, "Opcode {} is a builtin instruction", x),
+            RegisterError::Taken(x) => write!(f, "Opcode {} is already registered", x),
+            RegisterError::TooManyParams(n) => write!(f, "{} parameters is more than the {} an instruction can have", n, MAX_PARAMS),
+        }
+    }
+}
+
+impl std::error::Error for RegisterError {}
+
+/// Extension instructions by opcode. Install on a machine through
+/// `Machine::extensions`; opcodes not registered here still fail to decode.
+#[derive(Clone, Debug, Default)]
+pub struct Extensions<W = i64> {
+    by_opcode: HashMap<i64, Extension<W>>,
+}
+
+impl<W> Extensions<W> {
+    pub fn new() -> Self {
+        Extensions { by_opcode: HashMap::new() }
+    }
+
+    pub fn register(&mut self, opcode: i64, extension: Extension<W>) -> Result<(), RegisterError> {
+        if !(1..=99).contains(&opcode) {
+            return Err(RegisterError::OutOfRange(opcode));
+        }
+
+        if Instruction::parse(opcode).is_ok() {
+            return Err(RegisterError::Builtin(opcode));
+        }
+
+        if self.by_opcode.contains_key(&opcode) {
+            return Err(RegisterError::Taken(opcode));
+        }
+
+        if extension.params.len() > MAX_PARAMS {
+            return Err(RegisterError::TooManyParams(extension.params.len()));
+        }
+
+        self.by_opcode.insert(opcode, extension);
+        Ok(())
+    }
+
+    /// The extension claiming a raw instruction word, if any.
+    pub fn get(&self, raw: i64) -> Option<&Extension<W>> {
+        self.by_opcode.get(&(raw % 100))
+    }
+}
+
+impl<W: Word> Machine<W> {
+    /// Executes the extension instruction at `pc`, failing with
+    /// `UnknownOpcode` if no extension claims it. Like builtin instructions,
+    /// it is reported to the tracer and recorded in the history and profile.
+    pub(crate) fn exec_extension(&mut self) -> Result<State<W>, IntcodeError<W>> {
+        let pc = self.pc;
+        let raw = self.as_int(&self.memory.get(pc))?;
+
+        let extension = match self.extensions.as_ref().and_then(|extensions| extensions.get(raw)) {
+            Some(extension) => extension.clone(),
+            None => return Err(IntcodeError::UnknownOpcode { fault: self.fault() }),
+        };
+
+        let mut params = vec![];
+        let mut values = vec![];
+        let mut targets = vec![];
+
+        for (ii, param) in extension.params.iter().enumerate() {
+            let digit = raw / 10_i64.pow(ii as u32 + 2) % 10;
+            let mode = Mode::parse(digit).map_err(|_| IntcodeError::UnknownMode { fault: self.fault(), mode: digit })?;
+
+            let word = self.memory.get(pc + 1 + ii);
+
+            match param {
+                Param::Read => values.push(Some(self.get(mode, &word)?)),
+                Param::Write => {
+                    targets.push(self.addr(mode, &word)?);
+                    values.push(None);
+                }
+            }
+
+            params.push(word);
+        }
+
+        let args: Vec<W> = values.iter().flatten().cloned().collect();
+        let results = (extension.handler)(&args).map_err(|message| IntcodeError::Extension { fault: self.fault(), message })?;
+
+        if results.len() != targets.len() {
+            let message = format!("{} returned {} values for {} parameters", extension.mnemonic, results.len(), targets.len());
+            return Err(IntcodeError::Extension { fault: self.fault(), message });
+        }
+
+        let mut overwritten = vec![];
+
+        for (addr, val) in targets.iter().zip(&results) {
+            overwritten.push((*addr, self.memory.get(*addr)));
+            self.write(*addr, val.clone())?;
+        }
+
+        if let Some(history) = &mut self.history {
+            history.record(Change {
+                pc,
+                relative_base: self.relative_base,
+                writes: overwritten,
+                input: None,
+                output: None,
+            });
+        }
+
+        self.pc += extension.size();
+
+        let operation = Operation::Extension { word: raw, mnemonic: extension.mnemonic };
+
+        if self.tracing {
+            if let Some(tracer) = &mut self.tracer {
+                let writes = targets.into_iter().zip(results).collect();
+                tracer.record(&Event { pc, operation: operation.clone(), params, values, writes });
+            }
+        }
+
+        if let Some(profile) = &mut self.profile {
+            profile.record(pc, operation, self.pc);
+        }
+
+        Ok(State::Running)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::History;
+    use std::iter;
+
+    #[test]
+    fn test_extensions() {
+        let mut extensions = Extensions::new();
+
+        let div = Extension::new("div", &[Param::Read, Param::Read, Param::Write], |args: &[i64]| {
+            args[0].checked_div(args[1]).map(|x| vec![x]).ok_or_else(|| "Division by zero".to_string())
+        });
+
+        extensions.register(10, div).unwrap();
+
+        let noop = Extension::new("nop", &[], |_: &[i64]| Ok(vec![]));
+        assert_eq!(extensions.register(1, noop.clone()).unwrap_err(), RegisterError::Builtin(1));
+        assert_eq!(extensions.register(10, noop.clone()).unwrap_err(), RegisterError::Taken(10));
+        assert_eq!(extensions.register(100, noop).unwrap_err(), RegisterError::OutOfRange(100));
+
+        let wide = Extension::new("wide", &[Param::Read; MAX_PARAMS + 1], |_: &[i64]| Ok(vec![]));
+        assert_eq!(extensions.register(20, wide).unwrap_err(), RegisterError::TooManyParams(18));
+
+        let extensions = Some(Arc::new(extensions));
+
+        // div 17, 5, [7]; out [7]; hlt
+        let mut machine = Machine::new(vec![1110, 17, 5, 7, 4, 7, 99, 0]);
+        machine.extensions = extensions.clone();
+        assert_eq!(machine.execute(iter::empty()).collect::<Vec<_>>(), vec![3]);
+
+        let mut machine = Machine::new(vec![1110, 17, 0, 7, 99]);
+        machine.extensions = extensions.clone();
+        assert_eq!(machine.run().unwrap_err().to_string(), "Division by zero at 0 [1110,17,0,7]");
+
+        let mut machine = Machine::new(vec![11, 99]);
+        machine.extensions = extensions;
+        assert!(matches!(machine.run(), Err(IntcodeError::UnknownOpcode { .. })));
+    }
+
+    #[test]
+    fn test_history() {
+        let mut extensions = Extensions::new();
+        extensions.register(20, Extension::new("set", &[Param::Write], |_: &[i64]| Ok(vec![42]))).unwrap();
+        extensions.register(21, Extension::new("swap", &[Param::Write, Param::Write], |_: &[i64]| Ok(vec![1, 2]))).unwrap();
+        let extensions = Some(Arc::new(extensions));
+
+        // set [5]; hlt
+        let mut machine = Machine::new(vec![20, 5, 99, 0, 0, 7]);
+        machine.extensions = extensions.clone();
+        machine.history = Some(History::new());
+        for _ in machine.execute(iter::empty()) {}
+        assert_eq!(machine.memory.get(5), 42);
+
+        while machine.step_back() {}
+        assert_eq!((machine.pc, machine.memory.get(5)), (0, 7));
+
+        // swap [4], [4]; hlt: both writes are undone, last one first
+        let mut machine = Machine::new(vec![21, 4, 4, 99, 7]);
+        machine.extensions = extensions;
+        machine.history = Some(History::new());
+        for _ in machine.execute(iter::empty()) {}
+        assert_eq!(machine.memory.get(4), 2);
+
+        assert!(machine.last_write(4));
+        assert_eq!((machine.pc, machine.memory.get(4)), (0, 7));
+    }
+
+    #[test]
+    fn test_observed() {
+        let mut extensions = Extensions::new();
+        extensions.register(10, Extension::new("nop", &[], |_: &[i64]| Ok(vec![]))).unwrap();
+
+        let events = Arc::new(std::sync::Mutex::new(vec![]));
+        let tracer = {
+            let events = events.clone();
+            move |event: &Event| events.lock().unwrap().push(event.clone())
+        };
+
+        let mut machine = Machine::new(vec![10, 10, 10, 99]);
+        machine.extensions = Some(Arc::new(extensions));
+        machine.profile = Some(crate::profile::Profile::new());
+        machine.tracer = Some(Box::new(tracer));
+        machine.tracing = true;
+        for _ in machine.execute(iter::empty()) {}
+
+        let profile = machine.profile.unwrap();
+        assert_eq!(profile.total, 4);
+        assert_eq!(profile.opcodes()["nop"], 3);
+        assert_eq!(events.lock().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::VecDeque;
+
+use crate::{IntcodeError, Machine, State, Word};
+
+/// What it takes to undo one executed instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change<W = i64> {
+    pub pc: usize,
+    pub relative_base: i64,
+    /// The addresses written to and the values they held before, in the
+    /// order written.
+    pub writes: Vec<(usize, W)>,
+    /// The input value consumed by the instruction.
+    pub input: Option<W>,
+    /// The value output by the instruction.
+    pub output: Option<W>,
+}
+
+/// The undo log of a machine, oldest change first.
+#[derive(Clone, Debug, Default)]
+pub struct History<W = i64> {
+    pub changes: VecDeque<Change<W>>,
+    /// The most changes kept; older ones are dropped, and can no longer be
+    /// undone. Unlimited while `None`.
+    pub limit: Option<usize>,
+}
+
+impl<W> History<W> {
+    pub fn new() -> Self {
+        History { changes: VecDeque::new(), limit: None }
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        History { changes: VecDeque::new(), limit: Some(limit) }
+    }
+
+    /// Appends a change, dropping the oldest one if over the limit.
+    pub fn record(&mut self, change: Change<W>) {
+        self.changes.push_back(change);
+
+        if self.limit.is_some_and(|limit| self.changes.len() > limit) {
+            self.changes.pop_front();
+        }
+    }
+
+    /// Outputs produced since recording started, in order.
+    pub fn outputs(&self) -> impl Iterator<Item = &W> {
+        self.changes.iter().filter_map(|change| change.output.as_ref())
+    }
+}
+
+/// The outcome of `Machine::bisect`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bisection {
+    /// The current state is not bad.
+    Good,
+    /// The state was already bad at the start of the history.
+    BadFromStart,
+    /// The instruction at this position in the history made things go bad.
+    BadAfter(usize),
+}
+
+/// Reverse execution. These require `history` to be set before the steps
+/// that are to be undone are executed.
+impl<W: Word> Machine<W> {
+    /// Number of instructions recorded in the history.
+    pub fn position(&self) -> usize {
+        self.history.as_ref().map_or(0, |history| history.changes.len())
+    }
+
+    /// Undoes the last recorded instruction. Returns `false` if there is
+    /// nothing to undo.
+    pub fn step_back(&mut self) -> bool {
+        let change = match self.history.as_mut().and_then(|history| history.changes.pop_back()) {
+            Some(change) => change,
+            None => return false,
+        };
+
+        for (addr, val) in change.writes.into_iter().rev() {
+            // The address was written successfully before, so it is within the limit.
+            self.memory.set(addr, val).unwrap();
+        }
+
+        if let Some(val) = change.input {
+            self.input.push_front(val);
+        }
+
+        self.pc = change.pc;
+        self.relative_base = change.relative_base;
+        self.halted = false;
+
+        true
+    }
+
+    /// Moves to the state after `position` recorded instructions, stepping
+    /// backwards or re-executing forwards as needed. Forward replay stops
+    /// early if the machine halts or runs out of input.
+    pub fn seek(&mut self, position: usize) -> Result<(), IntcodeError<W>> {
+        while self.position() > position {
+            self.step_back();
+        }
+
+        while self.position() < position {
+            match self.step()? {
+                State::Running | State::Output(_) => {}
+                State::NeedsInput | State::Halted => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Steps back until just before the most recent write to `addr`, so that
+    /// `pc` points at the writing instruction. Returns `false`, having undone
+    /// the whole history, if there is no such write.
+    pub fn last_write(&mut self, addr: usize) -> bool {
+        loop {
+            let wrote = match self.history.as_ref().and_then(|history| history.changes.back()) {
+                Some(change) => change.writes.iter().any(|(a, _)| *a == addr),
+                None => return false,
+            };
+
+            self.step_back();
+
+            if wrote {
+                return true;
+            }
+        }
+    }
+
+    /// Finds the first recorded state in which `is_bad` holds, assuming it
+    /// keeps holding from then on, by binary search over the history.
+    ///
+    /// The machine is left in the state just before, about to execute the
+    /// instruction that made things go bad, or at the start of the history if
+    /// it was bad from there on. If the current state is not bad, nothing
+    /// changes.
+    pub fn bisect(&mut self, mut is_bad: impl FnMut(&Self) -> bool) -> Result<Bisection, IntcodeError<W>> {
+        if !is_bad(self) {
+            return Ok(Bisection::Good);
+        }
+
+        let (mut lo, mut hi) = (0, self.position());
+
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            self.seek(mid)?;
+
+            match is_bad(self) {
+                true => hi = mid,
+                false => lo = mid + 1,
+            }
+        }
+
+        if hi == 0 {
+            self.seek(0)?;
+            return Ok(Bisection::BadFromStart);
+        }
+
+        self.seek(hi - 1)?;
+        Ok(Bisection::BadAfter(hi - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reverse() {
+        // Reads two numbers and outputs 0, their sum, then their product.
+        let program = vec![3, 100, 3, 101, 104, 0, 1, 100, 101, 102, 4, 102, 2, 100, 101, 102, 4, 102, 99];
+
+        let mut machine = Machine::new(program.clone());
+        machine.history = Some(History::new());
+        machine.input.extend(vec![3, 4]);
+
+        let outputs: Vec<_> = machine.execute(std::iter::empty()).collect();
+        assert_eq!(outputs, vec![0, 7, 12]);
+        assert_eq!(machine.position(), 8);
+
+        assert!(machine.last_write(102));
+        assert_eq!(machine.pc, 12);
+        assert_eq!(machine.memory.get(102), 7);
+
+        let bad = |m: &Machine| m.history.as_ref().unwrap().outputs().any(|val| *val != 0);
+        assert_eq!(machine.bisect(bad), Ok(Bisection::BadAfter(4)));
+        assert_eq!(machine.pc, 10);
+        assert_eq!(machine.bisect(|_| false), Ok(Bisection::Good));
+        assert_eq!(machine.pc, 10);
+        assert_eq!(machine.bisect(|_| true), Ok(Bisection::BadFromStart));
+        assert_eq!(machine.pc, 0);
+
+        while machine.step_back() {}
+        assert_eq!(machine.pc, 0);
+        assert_eq!(&machine.memory.dense()[..program.len()], &program[..]);
+        assert!(machine.memory.dense()[program.len()..].iter().all(|val| *val == 0));
+        assert_eq!(machine.input, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_limit() {
+        // Counts [7] up forever.
+        let mut machine = Machine::new(vec![1001, 7, 1, 7, 1105, 1, 0, 0]);
+        machine.history = Some(History::with_limit(3));
+
+        for _ in 0..10 {
+            machine.step().unwrap();
+        }
+
+        assert_eq!(machine.position(), 3);
+        while machine.step_back() {}
+        assert_eq!((machine.pc, machine.memory.get(7)), (4, 4));
+    }
+}
@@ -0,0 +1,161 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    UnknownOpcode(i64),
+    UnknownMode(i64),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnknownOpcode(x) => write!(f, "Unknown instruction {}", x),
+            DecodeError::UnknownMode(x) => write!(f, "Unknown mode {}", x),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Position,
+    Value,
+    Relative,
+}
+
+impl Mode {
+    pub fn parse(raw: i64) -> Result<Self, DecodeError> {
+        match raw {
+            0 => Ok(Mode::Position),
+            1 => Ok(Mode::Value),
+            2 => Ok(Mode::Relative),
+            x => Err(DecodeError::UnknownMode(x)),
+        }
+    }
+
+    pub fn encode(&self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Value => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    Add(Mode, Mode, Mode),
+    Multiply(Mode, Mode, Mode),
+    Input(Mode),
+    Output(Mode),
+    Halt,
+    JumpIfTrue(Mode, Mode),
+    JumpIfFalse(Mode, Mode),
+    LessThan(Mode, Mode, Mode),
+    Equals(Mode, Mode, Mode),
+    AdjustBase(Mode),
+}
+
+impl Instruction {
+    pub fn parse(raw: i64) -> Result<Self, DecodeError> {
+        use Instruction::*;
+
+        let code = raw % 100;
+        let mode0 = || Mode::parse(raw / 100 % 10);
+        let mode1 = || Mode::parse(raw / 1000 % 10);
+        let mode2 = || Mode::parse(raw / 10000 % 10);
+
+        Ok(match code {
+            1 => Add(mode0()?, mode1()?, mode2()?),
+            2 => Multiply(mode0()?, mode1()?, mode2()?),
+            3 => Input(mode0()?),
+            4 => Output(mode0()?),
+            5 => JumpIfTrue(mode0()?, mode1()?),
+            6 => JumpIfFalse(mode0()?, mode1()?),
+            7 => LessThan(mode0()?, mode1()?, mode2()?),
+            8 => Equals(mode0()?, mode1()?, mode2()?),
+            9 => AdjustBase(mode0()?),
+            99 => Halt,
+            _ => return Err(DecodeError::UnknownOpcode(raw)),
+        })
+    }
+
+    pub fn opcode(&self) -> i64 {
+        use Instruction::*;
+
+        match self {
+            Add(..) => 1,
+            Multiply(..) => 2,
+            Input(_) => 3,
+            Output(_) => 4,
+            JumpIfTrue(..) => 5,
+            JumpIfFalse(..) => 6,
+            LessThan(..) => 7,
+            Equals(..) => 8,
+            AdjustBase(_) => 9,
+            Halt => 99,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        use Instruction::*;
+
+        match self {
+            Add(..) => "add",
+            Multiply(..) => "mul",
+            Input(_) => "in",
+            Output(_) => "out",
+            JumpIfTrue(..) => "jt",
+            JumpIfFalse(..) => "jf",
+            LessThan(..) => "lt",
+            Equals(..) => "eq",
+            AdjustBase(_) => "arb",
+            Halt => "hlt",
+        }
+    }
+
+    /// Modes of the parameters, in order.
+    pub fn modes(&self) -> Vec<Mode> {
+        use Instruction::*;
+
+        match *self {
+            Halt => vec![],
+            Input(m0) | Output(m0) | AdjustBase(m0) => vec![m0],
+            JumpIfTrue(m0, m1) | JumpIfFalse(m0, m1) => vec![m0, m1],
+            Add(m0, m1, m2) | Multiply(m0, m1, m2) | LessThan(m0, m1, m2) | Equals(m0, m1, m2) => vec![m0, m1, m2],
+        }
+    }
+
+    /// The canonical raw word for the instruction, i.e. the one `parse`
+    /// would turn back into `self`.
+    pub fn encode(&self) -> i64 {
+        self.modes()
+            .iter()
+            .enumerate()
+            .map(|(ii, mode)| mode.encode() * 10_i64.pow(ii as u32 + 2))
+            .sum::<i64>()
+            + self.opcode()
+    }
+
+    /// Index of the parameter the instruction writes to, if any.
+    pub fn written_param(&self) -> Option<usize> {
+        use Instruction::*;
+
+        match self {
+            Input(_) => Some(0),
+            Add(..) | Multiply(..) | LessThan(..) | Equals(..) => Some(2),
+            _ => None,
+        }
+    }
+
+    /// Number of words occupied by the instruction, including the opcode.
+    pub fn size(&self) -> usize {
+        match self {
+            Instruction::Halt => 1,
+            Instruction::Input(_) | Instruction::Output(_) | Instruction::AdjustBase(_) => 2,
+            Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) => 3,
+            Instruction::Add(..) | Instruction::Multiply(..) | Instruction::LessThan(..) | Instruction::Equals(..) => 4,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+pub mod analysis;
+pub mod ascii;
+pub mod asm;
+mod channel;
+mod compile;
+pub mod debugger;
+pub mod disasm;
+mod error;
+pub mod extension;
+mod history;
+mod instruction;
+mod machine;
+mod memory;
+pub mod network;
+mod predecode;
+pub mod profile;
+pub mod search;
+pub mod session;
+mod snapshot;
+pub mod symbolic;
+pub mod trace;
+mod word;
+
+use std::io;
+
+pub use channel::AsyncMachine;
+pub use error::{Fault, IntcodeError};
+pub use history::{Bisection, Change, History};
+pub use instruction::{DecodeError, Instruction, Mode};
+pub use machine::{Arithmetic, Backend, Machine, State, Yield};
+pub use memory::{Memory, MemoryLimitExceeded};
+pub use snapshot::Snapshot;
+pub use num_bigint::BigInt;
+pub use word::Word;
+
+pub type Program<W = i64> = Vec<W>;
+
+/// Parses comma-separated words, failing with `InvalidData` on the first
+/// word that is not a number.
+pub fn parse<W: Word>(source: &str) -> io::Result<Program<W>> {
+    source
+        .split(',')
+        .map(|s| s.trim())
+        .enumerate()
+        .map(|(ii, s)| {
+            s.parse::<W>()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid word {:?} at position {}", s, ii)))
+        })
+        .collect()
+}
+
+pub fn load<W: Word>(filename: &str) -> io::Result<Program<W>> {
+    parse(&std::fs::read_to_string(filename)?)
+}
+
+pub fn format<W: Word>(program: &[W]) -> String {
+    program.iter().map(|word| word.to_string()).collect::<Vec<_>>().join(",")
+}
@@ -0,0 +1,662 @@
+use std::collections::VecDeque;
+use std::iter;
+use std::sync::Arc;
+
+use crate::extension::Extensions;
+use crate::history::{Change, History};
+use crate::instruction::{DecodeError, Instruction, Mode};
+use crate::memory::Decoded;
+use crate::profile::Profile;
+use crate::snapshot::Snapshot;
+use crate::trace::{Event, Operation, Tracer};
+use crate::{Fault, IntcodeError, Memory, Program, Word};
+
+/// How `Add` and `Multiply` treat results that do not fit in the word type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arithmetic {
+    Checked,
+    Wrapping,
+}
+
+/// How `run` executes instructions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Decode and execute one instruction at a time.
+    Interpreter,
+    /// Translate straight-line code into closures, one block at a time.
+    /// Falls back to the interpreter for self-modified code, and while
+    /// tracing, recording history or profiling. Currently about half as
+    /// fast as the interpreter with its decode cache.
+    Compiled,
+}
+
+/// What the machine is doing after executing an instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub enum State<W = i64> {
+    Running,
+    NeedsInput,
+    Output(W),
+    Halted,
+}
+
+/// Why `run` returned.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Yield<W = i64> {
+    NeedsInput,
+    Output(W),
+    Halted,
+}
+
+pub struct Machine<W = i64> {
+    pub pc: usize,
+    pub relative_base: i64,
+    pub memory: Memory<W>,
+    pub input: VecDeque<W>,
+    pub arithmetic: Arithmetic,
+    pub halted: bool,
+    pub tracer: Option<Box<dyn Tracer<W> + Send + Sync>>,
+    /// Whether executed instructions are reported to `tracer`.
+    pub tracing: bool,
+    /// Whether decoded instructions are cached between steps.
+    pub decode_cache: bool,
+    pub backend: Backend,
+    /// Undo log for stepping backwards; not recorded while `None`.
+    pub history: Option<History<W>>,
+    /// Execution counts; not recorded while `None`.
+    pub profile: Option<Profile>,
+    /// Instructions to try when an opcode is not a builtin one.
+    pub extensions: Option<Arc<Extensions<W>>>,
+}
+
+/// Clones the machine state. The clone has no tracer, and shares the
+/// decoded instruction cache with the original.
+impl<W: Word> Clone for Machine<W> {
+    fn clone(&self) -> Self {
+        Machine {
+            pc: self.pc,
+            relative_base: self.relative_base,
+            memory: self.memory.clone(),
+            input: self.input.clone(),
+            arithmetic: self.arithmetic,
+            halted: self.halted,
+            tracer: None,
+            tracing: self.tracing,
+            decode_cache: self.decode_cache,
+            backend: self.backend,
+            history: self.history.clone(),
+            profile: self.profile.clone(),
+            extensions: self.extensions.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.pc = source.pc;
+        self.relative_base = source.relative_base;
+        self.memory.clone_from(&source.memory);
+        self.input.clone_from(&source.input);
+        self.arithmetic = source.arithmetic;
+        self.halted = source.halted;
+        self.tracer = None;
+        self.tracing = source.tracing;
+        self.decode_cache = source.decode_cache;
+        self.backend = source.backend;
+        self.history.clone_from(&source.history);
+        self.profile.clone_from(&source.profile);
+        self.extensions.clone_from(&source.extensions);
+    }
+}
+
+impl<W: Word> Machine<W> {
+    pub fn new(program: Program<W>) -> Self {
+        Machine {
+            pc: 0,
+            relative_base: 0,
+            memory: Memory::new(program),
+            input: VecDeque::new(),
+            arithmetic: Arithmetic::Checked,
+            halted: false,
+            tracer: None,
+            tracing: false,
+            decode_cache: true,
+            backend: Backend::Interpreter,
+            history: None,
+            profile: None,
+            extensions: None,
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot<W> {
+        Snapshot {
+            pc: self.pc,
+            relative_base: self.relative_base,
+            memory: self.memory.clone(),
+            input: self.input.clone(),
+            arithmetic: self.arithmetic,
+            halted: self.halted,
+        }
+    }
+
+    /// Resets the machine to a saved state, keeping the tracer. Recorded
+    /// history is discarded.
+    pub fn restore(&mut self, snapshot: Snapshot<W>) {
+        self.pc = snapshot.pc;
+        self.relative_base = snapshot.relative_base;
+        self.memory = snapshot.memory;
+        self.input = snapshot.input;
+        self.arithmetic = snapshot.arithmetic;
+        self.halted = snapshot.halted;
+
+        if let Some(history) = &mut self.history {
+            history.changes.clear();
+        }
+    }
+
+    /// Captures the instruction at `pc` for error reporting.
+    pub fn fault(&self) -> Fault<W> {
+        let raw = self.memory.get(self.pc).to_i64();
+        let extension = |raw| self.extensions.as_ref()?.get(raw).map(|extension| extension.size());
+
+        let size = match raw.map(Instruction::parse) {
+            Some(Ok(instruction)) => instruction.size(),
+            _ => raw.and_then(extension).unwrap_or(1),
+        };
+
+        Fault {
+            pc: self.pc,
+            words: (self.pc..self.pc + size).map(|addr| self.memory.get(addr)).collect(),
+        }
+    }
+
+    /// Decodes the instruction at `pc` along with its parameter words.
+    fn decode(&self) -> Result<Decoded<W>, IntcodeError<W>> {
+        let instruction = Instruction::parse(self.as_int(&self.memory.get(self.pc))?).map_err(|e| match e {
+            DecodeError::UnknownOpcode(_) => IntcodeError::UnknownOpcode { fault: self.fault() },
+            DecodeError::UnknownMode(mode) => IntcodeError::UnknownMode { fault: self.fault(), mode },
+        })?;
+
+        let size = instruction.size();
+        let param = |ii: usize| match ii + 1 < size {
+            true => self.memory.get(self.pc + 1 + ii),
+            false => W::from_i64(0),
+        };
+
+        Ok(Decoded {
+            instruction,
+            params: [param(0), param(1), param(2)],
+        })
+    }
+
+    /// Decodes the instruction at `pc`, going through the cache if enabled.
+    #[inline(always)]
+    fn fetch(&mut self) -> Result<Decoded<W>, IntcodeError<W>> {
+        if !self.decode_cache {
+            return self.decode();
+        }
+
+        if let Some(decoded) = self.memory.cached(self.pc) {
+            return Ok(decoded.clone());
+        }
+
+        let decoded = self.decode()?;
+        self.memory.cache(self.pc, decoded.clone());
+        Ok(decoded)
+    }
+
+    /// Interprets a word as an opcode, address or offset.
+    #[inline(always)]
+    pub(crate) fn as_int(&self, val: &W) -> Result<i64, IntcodeError<W>> {
+        match val.to_i64() {
+            Some(x) => Ok(x),
+            None => Err(IntcodeError::ValueOutOfRange { fault: self.fault(), value: val.clone() }),
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn as_addr(&self, val: i64) -> Result<usize, IntcodeError<W>> {
+        match val {
+            x if x < 0 => Err(IntcodeError::NegativeAddress { fault: self.fault(), addr: x }),
+            x => Ok(x as usize),
+        }
+    }
+
+    /// Adds `offset` to the relative base.
+    #[inline(always)]
+    pub(crate) fn relative(&self, offset: &W) -> Result<i64, IntcodeError<W>> {
+        match self.relative_base.checked_add(self.as_int(offset)?) {
+            Some(x) => Ok(x),
+            None => Err(IntcodeError::Overflow { fault: self.fault(), a: W::from_i64(self.relative_base), b: offset.clone() }),
+        }
+    }
+
+    /// Resolves the address referred to by a parameter.
+    #[inline(always)]
+    pub(crate) fn addr(&self, mode: Mode, param: &W) -> Result<usize, IntcodeError<W>> {
+        match mode {
+            Mode::Position => self.as_addr(self.as_int(param)?),
+            Mode::Relative => self.as_addr(self.relative(param)?),
+            Mode::Value => Err(IntcodeError::ValueModeWrite { fault: self.fault() }),
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn get(&self, mode: Mode, param: &W) -> Result<W, IntcodeError<W>> {
+        match mode {
+            Mode::Value => Ok(param.clone()),
+            _ => Ok(self.memory.get(self.addr(mode, param)?)),
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn write(&mut self, addr: usize, val: W) -> Result<(), IntcodeError<W>> {
+        self.memory.set(addr, val).map_err(|e| IntcodeError::MemoryLimitExceeded {
+            fault: self.fault(),
+            addr: e.addr,
+            limit: e.limit,
+        })
+    }
+
+    pub(crate) fn add(&self, a: W, b: W) -> Result<W, IntcodeError<W>> {
+        match self.arithmetic {
+            Arithmetic::Wrapping => Ok(a.wrapping_add(&b)),
+            Arithmetic::Checked => match a.checked_add(&b) {
+                Some(x) => Ok(x),
+                None => Err(IntcodeError::Overflow { fault: self.fault(), a, b }),
+            },
+        }
+    }
+
+    pub(crate) fn mul(&self, a: W, b: W) -> Result<W, IntcodeError<W>> {
+        match self.arithmetic {
+            Arithmetic::Wrapping => Ok(a.wrapping_mul(&b)),
+            Arithmetic::Checked => match a.checked_mul(&b) {
+                Some(x) => Ok(x),
+                None => Err(IntcodeError::Overflow { fault: self.fault(), a, b }),
+            },
+        }
+    }
+
+    /// Executes a single instruction.
+    ///
+    /// Returns `NeedsInput` without doing anything if the next instruction
+    /// reads input and the input queue is empty, and `Halted` once the
+    /// machine has halted.
+    #[inline(always)]
+    pub fn step(&mut self) -> Result<State<W>, IntcodeError<W>> {
+        if self.halted {
+            return Ok(State::Halted);
+        }
+
+        let pc = self.pc;
+
+        let Decoded { instruction, params } = match self.fetch() {
+            Ok(decoded) => decoded,
+            Err(IntcodeError::UnknownOpcode { .. }) if self.extensions.is_some() => return self.exec_extension(),
+            Err(e) => return Err(e),
+        };
+
+        let state = match (self.tracing && self.tracer.is_some()) || self.history.is_some() {
+            true => self.exec_observed(instruction, &params)?,
+            false => self.exec(instruction, &params)?,
+        };
+
+        if let Some(profile) = &mut self.profile {
+            if state != State::NeedsInput {
+                profile.record(pc, Operation::Builtin(instruction), self.pc);
+            }
+        }
+
+        Ok(state)
+    }
+
+    /// Executes `instruction`, reporting it to the tracer and recording it in
+    /// the history.
+    fn exec_observed(&mut self, instruction: Instruction, params: &[W; 3]) -> Result<State<W>, IntcodeError<W>> {
+        let tracing = self.tracing && self.tracer.is_some();
+        let relative_base = self.relative_base;
+        let mut event = self.trace_event(instruction, params);
+        let state = self.exec(instruction, params)?;
+
+        if state == State::NeedsInput {
+            return Ok(state);
+        }
+
+        let written: Vec<_> = event.writes.iter().map(|(addr, _)| (*addr, self.memory.get(*addr))).collect();
+
+        if let Some(history) = &mut self.history {
+            history.record(Change {
+                pc: event.pc,
+                relative_base,
+                writes: event.writes.clone(),
+                input: match instruction {
+                    Instruction::Input(_) => written.first().map(|(_, val)| val.clone()),
+                    _ => None,
+                },
+                output: match &state {
+                    State::Output(val) => Some(val.clone()),
+                    _ => None,
+                },
+            });
+        }
+
+        if tracing {
+            event.writes = written;
+            self.tracer.as_mut().unwrap().record(&event);
+        }
+
+        Ok(state)
+    }
+
+    /// Captures the operands of `instruction` before it is executed. The
+    /// written value is filled in afterwards.
+    fn trace_event(&self, instruction: Instruction, params: &[W; 3]) -> Event<W> {
+        let written = instruction.written_param();
+
+        let values = instruction
+            .modes()
+            .into_iter()
+            .enumerate()
+            .map(|(ii, mode)| match Some(ii) == written {
+                true => None,
+                false => self.get(mode, &params[ii]).ok(),
+            })
+            .collect();
+
+        let writes = written
+            .and_then(|ii| self.addr(instruction.modes()[ii], &params[ii]).ok())
+            .map(|addr| (addr, self.memory.get(addr)))
+            .into_iter()
+            .collect();
+
+        Event {
+            pc: self.pc,
+            operation: Operation::Builtin(instruction),
+            params: params[..instruction.size() - 1].to_vec(),
+            values,
+            writes,
+        }
+    }
+
+    #[inline(always)]
+    fn exec(&mut self, instruction: Instruction, params: &[W; 3]) -> Result<State<W>, IntcodeError<W>> {
+        match instruction {
+            Instruction::Halt => {
+                self.halted = true;
+                return Ok(State::Halted);
+            }
+            Instruction::Input(mode0) => {
+                let p0 = self.addr(mode0, &params[0])?;
+
+                match self.input.pop_front() {
+                    None => return Ok(State::NeedsInput),
+                    Some(val) => self.write(p0, val)?,
+                }
+
+                self.pc += 2;
+            }
+            Instruction::Output(mode0) => {
+                let p0 = self.get(mode0, &params[0])?;
+                self.pc += 2;
+                return Ok(State::Output(p0));
+            }
+            Instruction::Add(mode0, mode1, mode2) => {
+                let p0 = self.get(mode0, &params[0])?;
+                let p1 = self.get(mode1, &params[1])?;
+                let p2 = self.addr(mode2, &params[2])?;
+
+                self.write(p2, self.add(p0, p1)?)?;
+                self.pc += 4;
+            }
+            Instruction::Multiply(mode0, mode1, mode2) => {
+                let p0 = self.get(mode0, &params[0])?;
+                let p1 = self.get(mode1, &params[1])?;
+                let p2 = self.addr(mode2, &params[2])?;
+
+                self.write(p2, self.mul(p0, p1)?)?;
+                self.pc += 4;
+            }
+            Instruction::JumpIfTrue(mode0, mode1) => {
+                let p0 = self.get(mode0, &params[0])?;
+                let p1 = self.get(mode1, &params[1])?;
+
+                match p0.is_zero() {
+                    true => self.pc += 3,
+                    false => self.pc = self.as_addr(self.as_int(&p1)?)?,
+                }
+            }
+            Instruction::JumpIfFalse(mode0, mode1) => {
+                let p0 = self.get(mode0, &params[0])?;
+                let p1 = self.get(mode1, &params[1])?;
+
+                match p0.is_zero() {
+                    true => self.pc = self.as_addr(self.as_int(&p1)?)?,
+                    false => self.pc += 3,
+                }
+            }
+            Instruction::LessThan(mode0, mode1, mode2) => {
+                let p0 = self.get(mode0, &params[0])?;
+                let p1 = self.get(mode1, &params[1])?;
+                let p2 = self.addr(mode2, &params[2])?;
+
+                self.write(p2, W::from_i64(if p0 < p1 { 1 } else { 0 }))?;
+                self.pc += 4;
+            }
+            Instruction::Equals(mode0, mode1, mode2) => {
+                let p0 = self.get(mode0, &params[0])?;
+                let p1 = self.get(mode1, &params[1])?;
+                let p2 = self.addr(mode2, &params[2])?;
+
+                self.write(p2, W::from_i64(if p0 == p1 { 1 } else { 0 }))?;
+                self.pc += 4;
+            }
+            Instruction::AdjustBase(mode0) => {
+                let p0 = self.get(mode0, &params[0])?;
+                self.relative_base = self.relative(&p0)?;
+                self.pc += 2;
+            }
+        }
+
+        Ok(State::Running)
+    }
+
+    /// Executes instructions until the machine produces an output, halts, or
+    /// needs input that is not in the queue.
+    #[inline]
+    pub fn run(&mut self) -> Result<Yield<W>, IntcodeError<W>> {
+        let observed = (self.tracing && self.tracer.is_some()) || self.history.is_some() || self.profile.is_some();
+        let predecoded = self.backend == Backend::Interpreter && self.decode_cache && !observed;
+
+        if predecoded && !self.halted {
+            if let Some(res) = self.run_predecoded() {
+                return Ok(res);
+            }
+        }
+
+        self.run_interpreted(predecoded, self.backend == Backend::Compiled && !observed)
+    }
+
+    /// The rest of `run`, for when the fast path has stopped at an
+    /// instruction it leaves to the interpreter.
+    #[inline(never)]
+    fn run_interpreted(&mut self, predecoded: bool, compiled: bool) -> Result<Yield<W>, IntcodeError<W>> {
+        loop {
+            if compiled && !self.halted {
+                self.run_block()?;
+            }
+
+            let state = self.step()?;
+
+            if predecoded && state == State::Running {
+                if let Some(res) = self.run_predecoded() {
+                    return Ok(res);
+                }
+            }
+
+            match state {
+                State::Running => continue,
+                State::NeedsInput => return Ok(Yield::NeedsInput),
+                State::Output(val) => return Ok(Yield::Output(val)),
+                State::Halted => return Ok(Yield::Halted),
+            }
+        }
+    }
+
+    /// Runs the machine, yielding outputs as they are produced.
+    ///
+    /// Input is taken from the queue first, then pulled from `input` on
+    /// demand. The iterator ends either when the machine halts or when it
+    /// needs input but `input` is exhausted; check `halted` to tell the two
+    /// apart. After an error the iterator ends and `pc` points at the
+    /// faulting instruction.
+    pub fn try_execute<'a>(
+        &'a mut self,
+        mut input: impl Iterator<Item = W> + 'a,
+    ) -> impl Iterator<Item = Result<W, IntcodeError<W>>> + 'a {
+        let mut failed = false;
+
+        iter::from_fn(move || loop {
+            if failed {
+                return None;
+            }
+
+            match self.run() {
+                Ok(Yield::Halted) => return None,
+                Ok(Yield::Output(val)) => return Some(Ok(val)),
+                Ok(Yield::NeedsInput) => match input.next() {
+                    Some(val) => self.input.push_back(val),
+                    None => return None,
+                },
+                Err(e) => {
+                    failed = true;
+                    return Some(Err(e));
+                }
+            }
+        })
+    }
+
+    /// Like `try_execute`, but panics on errors.
+    pub fn execute<'a>(&'a mut self, input: impl Iterator<Item = W> + 'a) -> impl Iterator<Item = W> + 'a {
+        self.try_execute(input).map(|res| match res {
+            Ok(val) => val,
+            Err(e) => panic!("{}", e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BigInt;
+
+    fn run(program: Program, input: Vec<i64>) -> (Memory, Vec<i64>) {
+        let mut machine = Machine::new(program);
+        let output = machine.execute(input.into_iter()).collect();
+        (machine.memory, output)
+    }
+
+    #[test]
+    fn test_execute() {
+        assert_eq!(run(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50], vec![]).0.get(0), 3500);
+        assert_eq!(run(vec![1002, 4, 3, 4, 33], vec![]).0.get(4), 99);
+        assert_eq!(run(vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8], vec![8]).1, vec![1]);
+        assert_eq!(run(vec![3, 3, 1107, -1, 8, 3, 4, 3, 99], vec![9]).1, vec![0]);
+        assert_eq!(run(vec![109, 5, 203, 2, 204, 2, 99, 0], vec![42]).1, vec![42]);
+        assert_eq!(run(vec![104, 1125899, 99], vec![]).1, vec![1125899]);
+        assert_eq!(run(vec![1101, 2, 3, 100, 4, 100, 99], vec![]).1, vec![5]);
+        assert_eq!(run(vec![1101, 2, 3, 1000000, 4, 1000000, 99], vec![]).1, vec![5]);
+        assert_eq!(run(vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0], vec![]).1, vec![1219070632396864]);
+    }
+
+    #[test]
+    fn test_bigint() {
+        let program = crate::parse("1102,9223372036854775807,10,7,4,7,99,0").unwrap();
+        let output: Vec<BigInt> = Machine::new(program).execute(iter::empty()).collect();
+        assert_eq!(output, vec!["92233720368547758070".parse::<BigInt>().unwrap()]);
+    }
+
+    #[test]
+    fn test_run() {
+        let mut machine = Machine::new(vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8]);
+
+        assert_eq!(machine.run(), Ok(Yield::NeedsInput));
+        assert_eq!(machine.pc, 0);
+
+        machine.input.push_back(8);
+        assert_eq!(machine.step(), Ok(State::Running));
+        assert_eq!(machine.pc, 2);
+
+        assert_eq!(machine.run(), Ok(Yield::Output(1)));
+        assert_eq!(machine.run(), Ok(Yield::Halted));
+        assert_eq!(machine.run(), Ok(Yield::Halted));
+    }
+
+    #[test]
+    fn test_decode_cache() {
+        // outputs 42, then overwrites the operand of that output with input
+        let mut prototype = Machine::new(vec![104, 42, 3, 1, 1105, 1, 0]);
+        prototype.memory.prepare_cache();
+
+        let output: Vec<i64> = prototype.clone().execute(vec![5, 6].into_iter()).collect();
+        assert_eq!(output, vec![42, 5, 6]);
+
+        let output: Vec<i64> = prototype.clone().execute(iter::empty()).collect();
+        assert_eq!(output, vec![42]);
+
+        // resetting in place restores the overwritten operand
+        let mut amp = prototype.clone();
+        assert_eq!(amp.execute(vec![5].into_iter()).collect::<Vec<i64>>(), vec![42, 5]);
+        amp.clone_from(&prototype);
+        assert_eq!(amp.execute(iter::empty()).collect::<Vec<i64>>(), vec![42]);
+    }
+
+    #[test]
+    fn test_predecoded() {
+        let run = |program: &Program, cache: bool| {
+            let mut machine = Machine::new(program.clone());
+            machine.decode_cache = cache;
+            machine.memory.prepare_cache();
+            let res: Vec<_> = machine.try_execute(vec![7].into_iter()).collect();
+            (res, machine.pc, machine.halted)
+        };
+
+        let programs = vec![
+            // writes past the end of the program
+            vec![1101, 2, 3, 10, 4, 10, 99],
+            // writes through the relative base
+            vec![109, 10, 21101, 2, 3, 0, 204, 0, 99, 0, 0],
+            // stores input into an operand it then outputs
+            vec![3, 3, 104, 0, 99, 0],
+            // jumps to a negative address
+            vec![1105, 1, -1, 0],
+            // overflows once the loop has been decoded
+            vec![1002, 8, 2, 8, 1005, 8, 0, 99, 1],
+        ];
+
+        for program in &programs {
+            assert_eq!(run(program, true), run(program, false), "{:?}", program);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let errors = |program: Program| Machine::new(program).try_execute(iter::empty()).find_map(Result::err);
+
+        let fault = |pc, words| Fault { pc, words };
+
+        assert_eq!(errors(vec![1, 0, 0, 0, 42]), Some(IntcodeError::UnknownOpcode { fault: fault(4, vec![42]) }));
+        assert_eq!(errors(vec![301, 0, 0, 0]), Some(IntcodeError::UnknownMode { fault: fault(0, vec![301]), mode: 3 }));
+        assert_eq!(errors(vec![4, -1, 99]), Some(IntcodeError::NegativeAddress { fault: fault(0, vec![4, -1]), addr: -1 }));
+        assert_eq!(errors(vec![11101, 0, 0, 0, 99]), Some(IntcodeError::ValueModeWrite { fault: fault(0, vec![11101, 0, 0, 0]) }));
+        assert_eq!(
+            errors(vec![1102, i64::MAX, 2, 0, 99]),
+            Some(IntcodeError::Overflow { fault: fault(0, vec![1102, i64::MAX, 2, 0]), a: i64::MAX, b: 2 })
+        );
+        assert_eq!(
+            errors(vec![109, i64::MAX, 109, 1, 99]),
+            Some(IntcodeError::Overflow { fault: fault(2, vec![109, 1]), a: i64::MAX, b: 1 })
+        );
+        assert_eq!(
+            errors(vec![109, -1, 204, i64::MIN, 99]),
+            Some(IntcodeError::Overflow { fault: fault(2, vec![204, i64::MIN]), a: -1, b: i64::MIN })
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+use crate::compile::Block;
+use crate::predecode::Op;
+use crate::{Instruction, Program, Word};
+
+/// Writes this far past the end of the dense region extend it; anything
+/// further away is stored sparsely.
+const MAX_GROWTH: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryLimitExceeded {
+    pub addr: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for MemoryLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Address {} exceeds memory limit {}", self.addr, self.limit)
+    }
+}
+
+impl std::error::Error for MemoryLimitExceeded {}
+
+/// An instruction together with its parameter words, as fetched from memory.
+/// Parameters past the instruction's size are zero.
+#[derive(Clone, Debug)]
+pub(crate) struct Decoded<W> {
+    pub instruction: Instruction,
+    pub params: [W; 3],
+}
+
+/// Instructions decoded and blocks compiled from the dense region, as it was
+/// when the cache was created. `ops` holds `None` for instructions the fast
+/// path leaves to the interpreter.
+#[derive(Debug)]
+pub(crate) struct Cache<W> {
+    decoded: Vec<OnceLock<Decoded<W>>>,
+    ops: Vec<OnceLock<Option<Op>>>,
+    blocks: Vec<OnceLock<Arc<Block<W>>>>,
+}
+
+/// Zero-initialized, unbounded memory.
+///
+/// Addresses near the loaded program live in a `Vec` that grows on demand,
+/// while far-away addresses are kept in a `HashMap` so that a single write to
+/// a huge address does not allocate everything below it.
+///
+/// Memory also holds the decoded instruction and compiled block cache. The
+/// cache describes the dense region as it was when the cache was created, and
+/// is shared between clones; each clone tracks which of those words it has
+/// written since, and ignores cached instructions that may overlap them.
+#[derive(Debug)]
+pub struct Memory<W = i64> {
+    dense: Vec<W>,
+    sparse: HashMap<usize, W>,
+    limit: usize,
+    cache: Option<Arc<Cache<W>>>,
+    written: Vec<u64>,
+}
+
+impl<W: Clone> Clone for Memory<W> {
+    fn clone(&self) -> Self {
+        Memory {
+            dense: self.dense.clone(),
+            sparse: self.sparse.clone(),
+            limit: self.limit,
+            cache: self.cache.clone(),
+            written: self.written.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        if self.same_image(source) {
+            // Both differ from the cached image only in words they have
+            // written, so only those need copying.
+            for (ii, (bits, source_bits)) in self.written.iter_mut().zip(&source.written).enumerate() {
+                let mut changed = *bits | *source_bits;
+
+                while changed != 0 {
+                    let addr = ii * 64 + changed.trailing_zeros() as usize;
+                    self.dense[addr] = source.dense[addr].clone();
+                    changed &= changed - 1;
+                }
+
+                *bits = *source_bits;
+            }
+        } else {
+            self.dense.clone_from(&source.dense);
+            self.written.clone_from(&source.written);
+            self.cache.clone_from(&source.cache);
+        }
+
+        self.sparse.clone_from(&source.sparse);
+        self.limit = source.limit;
+    }
+}
+
+impl<W> Memory<W> {
+    /// Whether both share a cache and still hold exactly its region densely.
+    fn same_image(&self, other: &Self) -> bool {
+        match (&self.cache, &other.cache) {
+            (Some(a), Some(b)) => {
+                Arc::ptr_eq(a, b) && self.dense.len() == a.decoded.len() && other.dense.len() == a.decoded.len()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<W: Word> Memory<W> {
+    pub fn new(program: Program<W>) -> Self {
+        Memory {
+            dense: program,
+            sparse: HashMap::new(),
+            limit: usize::MAX,
+            cache: None,
+            written: Vec::new(),
+        }
+    }
+
+    /// Sets the number of addressable words; writing at or above `limit`
+    /// fails instead of allocating.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub(crate) fn dense(&self) -> &[W] {
+        &self.dense
+    }
+
+    pub(crate) fn sparse(&self) -> &HashMap<usize, W> {
+        &self.sparse
+    }
+
+    #[inline(always)]
+    pub fn get(&self, addr: usize) -> W {
+        match self.dense.get(addr).or_else(|| self.sparse.get(&addr)) {
+            Some(val) => val.clone(),
+            None => W::from_i64(0),
+        }
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, addr: usize, val: W) -> Result<(), MemoryLimitExceeded> {
+        if addr >= self.limit {
+            return Err(MemoryLimitExceeded { addr, limit: self.limit });
+        }
+
+        self.mark_written(addr);
+
+        if addr < self.dense.len() {
+            self.dense[addr] = val;
+        } else if addr - self.dense.len() < MAX_GROWTH {
+            self.grow(addr + 1);
+            self.dense[addr] = val;
+        } else {
+            self.sparse.insert(addr, val);
+        }
+
+        Ok(())
+    }
+
+    /// Marks the instructions that may contain `addr` as written: those
+    /// starting at `addr` or up to three words earlier.
+    #[inline(always)]
+    fn mark_written(&mut self, addr: usize) {
+        let start = addr.saturating_sub(3);
+        let bits: u64 = 0b1111 >> (start + 3 - addr);
+
+        if let Some(word) = self.written.get_mut(start / 64) {
+            *word |= bits << (start % 64);
+        }
+
+        if start % 64 > 60 {
+            if let Some(word) = self.written.get_mut(start / 64 + 1) {
+                *word |= bits >> (64 - start % 64);
+            }
+        }
+    }
+
+    /// Allocates the decoded instruction cache if there is none yet, so
+    /// that clones made from now on share it.
+    pub fn prepare_cache(&mut self) {
+        if self.cache.is_none() {
+            self.cache = Some(Arc::new(Cache {
+                decoded: (0..self.dense.len()).map(|_| OnceLock::new()).collect(),
+                ops: (0..self.dense.len()).map(|_| OnceLock::new()).collect(),
+                blocks: (0..self.dense.len()).map(|_| OnceLock::new()).collect(),
+            }));
+            self.written = vec![0; self.dense.len().div_ceil(64)];
+        }
+    }
+
+    /// Returns the cached instruction at `pc`, unless it may have been
+    /// overwritten since it was cached.
+    #[inline(always)]
+    pub(crate) fn cached(&self, pc: usize) -> Option<&Decoded<W>> {
+        match self.is_written(pc) {
+            true => None,
+            false => self.cache.as_ref()?.decoded.get(pc)?.get(),
+        }
+    }
+
+    /// Caches the instruction decoded at `pc`. Instructions that overlap
+    /// written words or extend past the cached region are not cached.
+    pub(crate) fn cache(&mut self, pc: usize, decoded: Decoded<W>) {
+        self.prepare_cache();
+
+        if self.is_original(pc, decoded.instruction.size()) {
+            let _ = self.cache.as_ref().unwrap().decoded[pc].set(decoded);
+        }
+    }
+
+    /// Returns the pre-decoded instruction at `pc`, decoding it on first
+    /// use, unless it may have been overwritten or the fast path leaves it
+    /// to the interpreter.
+    #[inline(always)]
+    pub(crate) fn op(&self, pc: usize) -> Option<&Op> {
+        if self.is_written(pc) {
+            return None;
+        }
+
+        let slot = self.cache.as_ref()?.ops.get(pc)?;
+
+        match slot.get() {
+            Some(op) => op.as_ref(),
+            None => self.predecode(pc, slot),
+        }
+    }
+
+    /// Decoding reads up to four words, which must all be original for the
+    /// result to be shared.
+    #[cold]
+    fn predecode<'a>(&self, pc: usize, slot: &'a OnceLock<Option<Op>>) -> Option<&'a Op> {
+        if !self.is_original(pc, 4) {
+            return None;
+        }
+
+        slot.get_or_init(|| Op::decode(self, pc)).as_ref()
+    }
+
+    /// Returns the block compiled at `pc`, if any.
+    pub(crate) fn block(&self, pc: usize) -> Option<Arc<Block<W>>> {
+        self.cache.as_ref()?.blocks.get(pc)?.get().cloned()
+    }
+
+    /// Caches the block compiled at `pc`, returning the block that ends up
+    /// cached there. `pc` must lie within the cached region.
+    pub(crate) fn cache_block(&mut self, pc: usize, block: Block<W>) -> Arc<Block<W>> {
+        self.prepare_cache();
+        self.cache.as_ref().unwrap().blocks[pc].get_or_init(|| Arc::new(block)).clone()
+    }
+
+    /// Whether the instruction of `size` words at `pc` lies within the cached
+    /// region and has not been written since the cache was created.
+    pub(crate) fn is_original(&self, pc: usize, size: usize) -> bool {
+        match &self.cache {
+            Some(cache) => pc + size <= cache.decoded.len() && !self.is_written(pc),
+            None => false,
+        }
+    }
+
+    /// Whether a write may have touched an instruction starting at `pc`.
+    #[inline(always)]
+    pub(crate) fn is_written(&self, pc: usize) -> bool {
+        self.written.get(pc / 64).is_some_and(|bits| bits >> (pc % 64) & 1 == 1)
+    }
+
+    fn grow(&mut self, len: usize) {
+        for addr in self.dense.len()..len {
+            let val = self.sparse.remove(&addr).unwrap_or_else(|| W::from_i64(0));
+            self.dense.push(val);
+        }
+    }
+}
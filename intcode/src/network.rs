@@ -0,0 +1,384 @@
+use std::fmt;
+
+use crate::{IntcodeError, Machine, Word, Yield};
+
+/// A machine in a network, along with everything it has output so far. On a
+/// `PacketNetwork`, only the words of a packet not yet complete are kept.
+pub struct Node<W = i64> {
+    pub name: String,
+    pub machine: Machine<W>,
+    pub outputs: Vec<W>,
+}
+
+/// How a network run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Every machine has halted.
+    Halted,
+    /// Some machines are still waiting for input that will never come, or
+    /// the monitor stopped the network.
+    Quiescent,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NodeError<W = i64> {
+    pub node: String,
+    pub error: IntcodeError<W>,
+}
+
+impl<W: Word> fmt::Display for NodeError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.node, self.error)
+    }
+}
+
+impl<W: Word> std::error::Error for NodeError<W> {}
+
+/// Machines connected by directed edges. Every output of a node is sent to
+/// the input of each node it is connected to, in the order the edges were
+/// added.
+pub struct Network<W = i64> {
+    pub nodes: Vec<Node<W>>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl<W: Word> Default for Network<W> {
+    fn default() -> Self {
+        Network::new()
+    }
+}
+
+impl<W: Word> Network<W> {
+    pub fn new() -> Self {
+        Network { nodes: vec![], edges: vec![] }
+    }
+
+    /// Adds a node with `input` queued up, returning its index.
+    pub fn add(&mut self, name: &str, mut machine: Machine<W>, input: impl IntoIterator<Item = W>) -> usize {
+        machine.input.extend(input);
+
+        self.nodes.push(Node {
+            name: name.to_string(),
+            machine,
+            outputs: vec![],
+        });
+
+        self.nodes.len() - 1
+    }
+
+    /// Sends the outputs of node `from` to node `to`.
+    ///
+    /// Panics if either node does not exist.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        let len = self.nodes.len();
+        assert!(from < len && to < len, "cannot connect {} to {}: network has {} nodes", from, to, len);
+
+        self.edges.push((from, to));
+    }
+
+    pub fn node(&self, name: &str) -> Option<&Node<W>> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+
+    /// Runs the nodes in turn, each until it halts or runs out of input,
+    /// until all of them have halted or none of them can make progress.
+    pub fn run(&mut self) -> Result<Outcome, NodeError<W>> {
+        loop {
+            let mut active = false;
+
+            for ii in 0..self.nodes.len() {
+                let outputs = self.run_node(ii)?;
+                active |= !outputs.is_empty();
+
+                for &(_, to) in self.edges.iter().filter(|(from, _)| *from == ii) {
+                    self.nodes[to].machine.input.extend(outputs.iter().cloned());
+                }
+            }
+
+            if !active {
+                break;
+            }
+        }
+
+        match self.nodes.iter().all(|node| node.machine.halted) {
+            true => Ok(Outcome::Halted),
+            false => Ok(Outcome::Quiescent),
+        }
+    }
+
+    /// Runs a single node until it halts or needs input, returning its
+    /// outputs.
+    fn run_node(&mut self, ii: usize) -> Result<Vec<W>, NodeError<W>> {
+        let node = &mut self.nodes[ii];
+        let mut outputs = vec![];
+
+        loop {
+            match node.machine.run() {
+                Ok(Yield::Output(val)) => outputs.push(val),
+                Ok(_) => break,
+                Err(error) => return Err(NodeError { node: node.name.clone(), error }),
+            }
+        }
+
+        node.outputs.extend(outputs.iter().cloned());
+        Ok(outputs)
+    }
+}
+
+/// A packet sent by a machine on a `PacketNetwork`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packet<W = i64> {
+    pub src: usize,
+    pub dest: W,
+    pub x: W,
+    pub y: W,
+}
+
+/// Watches the traffic on a `PacketNetwork`.
+pub trait Monitor<W = i64> {
+    /// Observes every packet sent; returning `false` stops the network.
+    fn packet(&mut self, packet: &Packet<W>) -> bool;
+
+    /// Called whenever every machine is waiting for input and no packets are
+    /// in flight. Returns packets to send; if there are none, the network
+    /// stops.
+    fn idle(&mut self) -> Vec<Packet<W>>;
+}
+
+/// Keeps the last packet sent to `address`, and sends it on to machine 0
+/// whenever the network is idle. Stops the network instead if it would send
+/// the same `y` twice in a row.
+pub struct Nat<W = i64> {
+    pub address: i64,
+    pub last: Option<Packet<W>>,
+    /// The `y` values sent to machine 0 so far.
+    pub sent: Vec<W>,
+}
+
+impl<W: Word> Nat<W> {
+    pub fn new(address: i64) -> Self {
+        Nat { address, last: None, sent: vec![] }
+    }
+}
+
+impl<W: Word> Monitor<W> for Nat<W> {
+    fn packet(&mut self, packet: &Packet<W>) -> bool {
+        if packet.dest.to_i64() == Some(self.address) {
+            self.last = Some(packet.clone());
+        }
+
+        true
+    }
+
+    fn idle(&mut self) -> Vec<Packet<W>> {
+        let last = match &self.last {
+            Some(last) if self.sent.last() != Some(&last.y) => last,
+            _ => return vec![],
+        };
+
+        self.sent.push(last.y.clone());
+
+        vec![Packet {
+            src: self.address as usize,
+            dest: W::from_i64(0),
+            x: last.x.clone(),
+            y: last.y.clone(),
+        }]
+    }
+}
+
+/// Machines that exchange packets by address. Each machine is told its
+/// address as its first input, sends packets as `dest, x, y` outputs, and
+/// reads `x, y` pairs of the packets sent to it, or -1 if there are none.
+/// Packets sent to addresses without a machine only reach the monitor.
+pub struct PacketNetwork<W = i64> {
+    pub nodes: Vec<Node<W>>,
+}
+
+impl<W: Word> PacketNetwork<W> {
+    /// Creates a network of machines with addresses `0..machines.len()`.
+    pub fn new(machines: Vec<Machine<W>>) -> Self {
+        let nodes = machines
+            .into_iter()
+            .enumerate()
+            .map(|(address, mut machine)| {
+                machine.input.push_back(W::from_i64(address as i64));
+
+                Node {
+                    name: address.to_string(),
+                    machine,
+                    outputs: vec![],
+                }
+            })
+            .collect();
+
+        PacketNetwork { nodes }
+    }
+
+    /// Runs the machines in turn until the monitor stops the network or all
+    /// machines halt.
+    pub fn run(&mut self, monitor: &mut impl Monitor<W>) -> Result<Outcome, NodeError<W>> {
+        loop {
+            let mut idle = true;
+
+            for ii in 0..self.nodes.len() {
+                idle &= self.nodes[ii].machine.input.is_empty();
+
+                for packet in self.run_node(ii)? {
+                    idle = false;
+
+                    if !self.send(packet, monitor) {
+                        return Ok(Outcome::Quiescent);
+                    }
+                }
+            }
+
+            if self.nodes.iter().all(|node| node.machine.halted) {
+                return Ok(Outcome::Halted);
+            }
+
+            if idle {
+                let packets = monitor.idle();
+
+                if packets.is_empty() {
+                    return Ok(Outcome::Quiescent);
+                }
+
+                for packet in packets {
+                    self.deliver(packet);
+                }
+            }
+        }
+    }
+
+    /// Runs a single machine until it halts or needs input, giving it -1
+    /// once if its input queue runs dry. Returns the packets it sent.
+    fn run_node(&mut self, ii: usize) -> Result<Vec<Packet<W>>, NodeError<W>> {
+        let node = &mut self.nodes[ii];
+        let mut starved = false;
+        let mut packets = vec![];
+
+        loop {
+            match node.machine.run() {
+                Ok(Yield::Output(val)) => node.outputs.push(val),
+                Ok(Yield::NeedsInput) if !starved => {
+                    starved = true;
+                    node.machine.input.push_back(W::from_i64(-1));
+                }
+                Ok(_) => break,
+                Err(error) => return Err(NodeError { node: node.name.clone(), error }),
+            }
+
+            if node.outputs.len() == 3 {
+                let mut words = node.outputs.drain(..);
+
+                packets.push(Packet {
+                    src: ii,
+                    dest: words.next().unwrap(),
+                    x: words.next().unwrap(),
+                    y: words.next().unwrap(),
+                });
+            }
+        }
+
+        Ok(packets)
+    }
+
+    fn send(&mut self, packet: Packet<W>, monitor: &mut impl Monitor<W>) -> bool {
+        let keep_going = monitor.packet(&packet);
+        self.deliver(packet);
+        keep_going
+    }
+
+    fn deliver(&mut self, packet: Packet<W>) {
+        let dest = packet.dest.to_i64().filter(|dest| *dest >= 0).map(|dest| dest as usize);
+
+        if let Some(node) = dest.and_then(|dest| self.nodes.get_mut(dest)) {
+            node.machine.input.push_back(packet.x);
+            node.machine.input.push_back(packet.y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amplifiers(source: &str, phases: &[i64], feedback: bool) -> Network {
+        let program = crate::parse(source).unwrap();
+        let mut network = Network::new();
+
+        for (ii, &phase) in phases.iter().enumerate() {
+            let input = if ii == 0 { vec![phase, 0] } else { vec![phase] };
+            network.add(&format!("amp-{}", ii), Machine::new(program.clone()), input);
+        }
+
+        for ii in 1..phases.len() {
+            network.connect(ii - 1, ii);
+        }
+
+        if feedback {
+            network.connect(phases.len() - 1, 0);
+        }
+
+        network
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let mut network = amplifiers("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0", &[4, 3, 2, 1, 0], false);
+        assert_eq!(network.run(), Ok(Outcome::Halted));
+        assert_eq!(network.node("amp-4").unwrap().outputs, vec![43210]);
+    }
+
+    #[test]
+    fn test_ring() {
+        let source = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+        let mut network = amplifiers(source, &[9, 8, 7, 6, 5], true);
+        assert_eq!(network.run(), Ok(Outcome::Halted));
+        assert_eq!(network.nodes[4].outputs.last(), Some(&139629729));
+    }
+
+    #[test]
+    fn test_quiescent() {
+        let mut network = Network::new();
+        network.add("echo", Machine::new(vec![3, 7, 4, 7, 1105, 1, 0]), vec![1, 2]);
+        assert_eq!(network.run(), Ok(Outcome::Quiescent));
+        assert_eq!(network.nodes[0].outputs, vec![1, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot connect 0 to 1: network has 1 nodes")]
+    fn test_connect_missing_node() {
+        let mut network: Network = Network::new();
+        network.add("echo", Machine::new(vec![3, 7, 4, 7, 1105, 1, 0]), vec![]);
+        network.connect(0, 1);
+    }
+
+    #[test]
+    fn test_packet_network() {
+        let program = crate::asm::assemble(
+            "
+                in [addr]
+                mul [addr], 2, [y]
+                out 255
+                out [addr]
+                out [y]
+            loop:
+                in [tmp]
+                jt 1, loop
+            addr: .data 0
+            y: .data 0
+            tmp: .data 0
+        ",
+        )
+        .unwrap();
+
+        let mut network = PacketNetwork::new(vec![Machine::new(program.clone()), Machine::new(program)]);
+        let mut nat = Nat::new(255);
+
+        assert_eq!(network.run(&mut nat), Ok(Outcome::Quiescent));
+        assert_eq!(nat.last, Some(Packet { src: 1, dest: 255, x: 1, y: 2 }));
+        assert_eq!(nat.sent, vec![2]);
+    }
+}
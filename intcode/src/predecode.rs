@@ -0,0 +1,179 @@
+use std::convert::TryFrom;
+
+use crate::{Arithmetic, Instruction, Machine, Memory, Mode, Word, Yield};
+
+/// A parameter with its addressing resolved when the instruction was
+/// decoded. A value parameter is read from its own word, which cannot have
+/// changed while the instruction is still cached.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Operand {
+    Address(usize),
+    Relative(i64),
+}
+
+impl Operand {
+    /// Returns `None` for parameters that can only fail, leaving the error to
+    /// the interpreter.
+    fn new<W: Word>(mode: Mode, addr: usize, param: W) -> Option<Self> {
+        match mode {
+            Mode::Value => Some(Operand::Address(addr)),
+            Mode::Position => param.to_i64().filter(|addr| *addr >= 0).map(|addr| Operand::Address(addr as usize)),
+            Mode::Relative => param.to_i64().map(Operand::Relative),
+        }
+    }
+}
+
+/// An instruction decoded once into the form the fast path executes.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Op {
+    Add(Operand, Operand, Operand),
+    Multiply(Operand, Operand, Operand),
+    Input(Operand),
+    Output(Operand),
+    JumpIfTrue(Operand, Operand),
+    JumpIfFalse(Operand, Operand),
+    LessThan(Operand, Operand, Operand),
+    Equals(Operand, Operand, Operand),
+    AdjustBase(Operand),
+    Halt,
+}
+
+impl Op {
+    /// Decodes the instruction at `pc`, returning `None` if it is not a
+    /// builtin instruction or one of its parameters can only fail.
+    pub(crate) fn decode<W: Word>(memory: &Memory<W>, pc: usize) -> Option<Self> {
+        let instruction = Instruction::parse(memory.get(pc).to_i64()?).ok()?;
+        let read = |ii: usize, mode| Operand::new(mode, pc + 1 + ii, memory.get(pc + 1 + ii));
+
+        let write = |ii: usize, mode| match mode {
+            Mode::Value => None,
+            mode => read(ii, mode),
+        };
+
+        Some(match instruction {
+            Instruction::Add(m0, m1, m2) => Op::Add(read(0, m0)?, read(1, m1)?, write(2, m2)?),
+            Instruction::Multiply(m0, m1, m2) => Op::Multiply(read(0, m0)?, read(1, m1)?, write(2, m2)?),
+            Instruction::Input(m0) => Op::Input(write(0, m0)?),
+            Instruction::Output(m0) => Op::Output(read(0, m0)?),
+            Instruction::JumpIfTrue(m0, m1) => Op::JumpIfTrue(read(0, m0)?, read(1, m1)?),
+            Instruction::JumpIfFalse(m0, m1) => Op::JumpIfFalse(read(0, m0)?, read(1, m1)?),
+            Instruction::LessThan(m0, m1, m2) => Op::LessThan(read(0, m0)?, read(1, m1)?, write(2, m2)?),
+            Instruction::Equals(m0, m1, m2) => Op::Equals(read(0, m0)?, read(1, m1)?, write(2, m2)?),
+            Instruction::AdjustBase(m0) => Op::AdjustBase(read(0, m0)?),
+            Instruction::Halt => Op::Halt,
+        })
+    }
+}
+
+/// The fast path for instructions in the decode cache. Every helper returns
+/// `None` before changing anything if the instruction could fail or needs
+/// memory outside the dense region, and the interpreter takes over.
+impl<W: Word> Machine<W> {
+    #[inline(always)]
+    fn resolve(&self, operand: Operand) -> Option<usize> {
+        match operand {
+            Operand::Address(addr) => Some(addr),
+            Operand::Relative(offset) => usize::try_from(self.relative_base.checked_add(offset)?).ok(),
+        }
+    }
+
+    #[inline(always)]
+    fn load(&self, operand: Operand) -> Option<W> {
+        self.memory.dense().get(self.resolve(operand)?).cloned()
+    }
+
+    /// Resolves a written parameter to an address that can be stored to
+    /// without failing or growing memory.
+    #[inline(always)]
+    fn target(&self, operand: Operand) -> Option<usize> {
+        let addr = self.resolve(operand)?;
+
+        match addr < self.memory.dense().len() && addr < self.memory.limit() {
+            true => Some(addr),
+            false => None,
+        }
+    }
+
+    #[inline(always)]
+    fn jump(&self, operand: Operand) -> Option<usize> {
+        usize::try_from(self.load(operand)?.to_i64()?).ok()
+    }
+
+    /// Executes instructions from the decode cache until one outputs, halts
+    /// or needs input that is not in the queue. Returns `None` instead,
+    /// without executing it, at the first instruction that is not cached or
+    /// that it leaves to the interpreter.
+    #[inline(always)]
+    pub(crate) fn run_predecoded(&mut self) -> Option<Yield<W>> {
+        loop {
+            let op = *self.memory.op(self.pc)?;
+
+            match op {
+                Op::Add(a, b, c) => {
+                    let (a, b, c) = (self.load(a)?, self.load(b)?, self.target(c)?);
+
+                    let val = match self.arithmetic {
+                        Arithmetic::Checked => a.checked_add(&b)?,
+                        Arithmetic::Wrapping => a.wrapping_add(&b),
+                    };
+
+                    self.memory.set(c, val).ok()?;
+                    self.pc += 4;
+                }
+                Op::Multiply(a, b, c) => {
+                    let (a, b, c) = (self.load(a)?, self.load(b)?, self.target(c)?);
+
+                    let val = match self.arithmetic {
+                        Arithmetic::Checked => a.checked_mul(&b)?,
+                        Arithmetic::Wrapping => a.wrapping_mul(&b),
+                    };
+
+                    self.memory.set(c, val).ok()?;
+                    self.pc += 4;
+                }
+                Op::Input(a) => {
+                    let a = self.target(a)?;
+
+                    match self.input.pop_front() {
+                        Some(val) => self.memory.set(a, val).ok()?,
+                        None => return Some(Yield::NeedsInput),
+                    }
+
+                    self.pc += 2;
+                }
+                Op::Output(a) => {
+                    let a = self.load(a)?;
+                    self.pc += 2;
+                    return Some(Yield::Output(a));
+                }
+                Op::JumpIfTrue(a, b) => match self.load(a)?.is_zero() {
+                    true => self.pc += 3,
+                    false => self.pc = self.jump(b)?,
+                },
+                Op::JumpIfFalse(a, b) => match self.load(a)?.is_zero() {
+                    true => self.pc = self.jump(b)?,
+                    false => self.pc += 3,
+                },
+                Op::LessThan(a, b, c) => {
+                    let (a, b, c) = (self.load(a)?, self.load(b)?, self.target(c)?);
+                    self.memory.set(c, W::from_i64(if a < b { 1 } else { 0 })).ok()?;
+                    self.pc += 4;
+                }
+                Op::Equals(a, b, c) => {
+                    let (a, b, c) = (self.load(a)?, self.load(b)?, self.target(c)?);
+                    self.memory.set(c, W::from_i64(if a == b { 1 } else { 0 })).ok()?;
+                    self.pc += 4;
+                }
+                Op::AdjustBase(a) => {
+                    let offset = self.load(a)?.to_i64()?;
+                    self.relative_base = self.relative_base.checked_add(offset)?;
+                    self.pc += 2;
+                }
+                Op::Halt => {
+                    self.halted = true;
+                    return Some(Yield::Halted);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use crate::trace::Operation;
+use crate::Instruction;
+
+/// Execution counts collected while a machine runs.
+///
+/// Code is split into blocks dynamically: a block starts at address 0 and
+/// at every taken jump target, and extends until the next taken jump.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    pub total: u64,
+    /// Executions per pc. Self-modified code shows up as several
+    /// instructions at the same pc.
+    pub instructions: HashMap<(usize, Operation), u64>,
+    /// Instructions executed per block, keyed by the block's start address.
+    pub blocks: HashMap<usize, u64>,
+    block: usize,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Profile::default()
+    }
+
+    pub fn record(&mut self, pc: usize, operation: Operation, next_pc: usize) {
+        let jumped = match operation {
+            Operation::Builtin(instruction @ Instruction::JumpIfTrue(..))
+            | Operation::Builtin(instruction @ Instruction::JumpIfFalse(..)) => next_pc != pc + instruction.size(),
+            _ => false,
+        };
+
+        self.total += 1;
+        *self.instructions.entry((pc, operation)).or_insert(0) += 1;
+        *self.blocks.entry(self.block).or_insert(0) += 1;
+
+        if jumped {
+            self.block = next_pc;
+        }
+    }
+
+    /// Adds the counts of another profile, e.g. from another run of the same
+    /// program.
+    pub fn merge(&mut self, other: &Profile) {
+        self.total += other.total;
+
+        for (key, count) in &other.instructions {
+            *self.instructions.entry(key.clone()).or_insert(0) += count;
+        }
+
+        for (block, count) in &other.blocks {
+            *self.blocks.entry(*block).or_insert(0) += count;
+        }
+    }
+
+    /// Executions per mnemonic.
+    pub fn opcodes(&self) -> BTreeMap<&str, u64> {
+        let mut res = BTreeMap::new();
+
+        for ((_, operation), count) in &self.instructions {
+            *res.entry(operation.mnemonic()).or_insert(0) += count;
+        }
+
+        res
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        100.0 * count as f64 / self.total.max(1) as f64
+    }
+
+    /// Writes a summary: totals, counts per opcode, and the `top` most
+    /// executed instructions and blocks.
+    pub fn write_report(&self, mut out: impl Write, top: usize) -> io::Result<()> {
+        writeln!(out, "total instructions: {}", self.total)?;
+
+        let mut opcodes: Vec<_> = self.opcodes().into_iter().collect();
+        opcodes.sort_by_key(|(mnemonic, count)| (std::cmp::Reverse(*count), *mnemonic));
+
+        writeln!(out, "\nby opcode:")?;
+        for (mnemonic, count) in opcodes {
+            writeln!(out, "  {:<6} {:>12} {:>6.2}%", mnemonic, count, self.percent(count))?;
+        }
+
+        let mut instructions: Vec<_> = self.instructions.iter().collect();
+        instructions.sort_by_key(|((pc, _), count)| (std::cmp::Reverse(**count), *pc));
+
+        writeln!(out, "\nhottest instructions:")?;
+        for ((pc, operation), count) in instructions.into_iter().take(top) {
+            writeln!(out, "  {:>6}: {:<6} {:>12} {:>6.2}%", pc, operation.mnemonic(), count, self.percent(*count))?;
+        }
+
+        let mut blocks: Vec<_> = self.blocks.iter().collect();
+        blocks.sort_by_key(|(start, count)| (std::cmp::Reverse(**count), **start));
+
+        writeln!(out, "\nhottest blocks:")?;
+        for (start, count) in blocks.into_iter().take(top) {
+            writeln!(out, "  {:>6} {:>12} {:>6.2}%", start, count, self.percent(*count))?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes block counts in the folded-stack format read by flamegraph
+    /// tools, one `block_<start> <count>` line per block.
+    pub fn write_folded(&self, mut out: impl Write) -> io::Result<()> {
+        let mut blocks: Vec<_> = self.blocks.iter().collect();
+        blocks.sort();
+
+        for (start, count) in blocks {
+            writeln!(out, "block_{} {}", start, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Machine, Mode};
+    use std::iter;
+
+    fn profile(input: i64) -> Profile {
+        // counts down from the input, outputting each value
+        let program = crate::asm::assemble(
+            "
+                in [n]
+            loop:
+                out [n]
+                add [n], -1, [n]
+                jt [n], loop
+                hlt
+            n: .data 0
+        ",
+        )
+        .unwrap();
+
+        let mut machine = Machine::new(program);
+        machine.input.push_back(input);
+        machine.profile = Some(Profile::new());
+        machine.execute(iter::empty()).count();
+        machine.profile.unwrap()
+    }
+
+    #[test]
+    fn test_profile() {
+        let mut profile = profile(3);
+
+        assert_eq!(profile.total, 11);
+        assert_eq!(profile.instructions[&(2, Operation::Builtin(Instruction::Output(Mode::Position)))], 3);
+        assert_eq!(profile.blocks, vec![(0, 4), (2, 7)].into_iter().collect());
+
+        profile.merge(&self::profile(1));
+        assert_eq!(profile.total, 16);
+        assert_eq!(profile.opcodes()["out"], 4);
+
+        let mut folded = vec![];
+        profile.write_folded(&mut folded).unwrap();
+        assert_eq!(String::from_utf8(folded).unwrap(), "block_0 9\nblock_2 7\n");
+
+        let mut report = vec![];
+        profile.write_report(&mut report, 1).unwrap();
+
+        let report = String::from_utf8(report).unwrap();
+        assert!(report.starts_with("total instructions: 16\n\nby opcode:\n  add               4  25.00%\n"));
+        assert!(report.ends_with(
+            "hottest instructions:\n       2: out               4  25.00%\n\n\
+             hottest blocks:\n       0            9  56.25%\n"
+        ));
+    }
+}
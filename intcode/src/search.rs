@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// The number of worker threads to use by default.
+pub fn threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Evaluates `f` on `candidates` across `threads` worker threads, returning
+/// the first candidate, in order, for which it returns `Some`.
+///
+/// Candidates are handed out in order, and once a match is found no
+/// candidates after it are started, so the result does not depend on how
+/// the work happens to be scheduled.
+pub fn find_first<T, R, F>(candidates: &[T], threads: usize, f: F) -> Option<(usize, R)>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> Option<R> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let found = AtomicUsize::new(usize::MAX);
+    let results = Mutex::new(vec![]);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let ii = next.fetch_add(1, Ordering::SeqCst);
+
+                if ii >= candidates.len() || ii > found.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                if let Some(res) = f(&candidates[ii]) {
+                    found.fetch_min(ii, Ordering::SeqCst);
+                    results.lock().unwrap().push((ii, res));
+                }
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_iter().min_by_key(|(ii, _)| *ii)
+}
+
+/// Evaluates `f` on all `candidates` across `threads` worker threads,
+/// returning the index and value of the largest result. Ties go to the
+/// earliest candidate.
+pub fn max_by_key<T, K, F>(candidates: &[T], threads: usize, f: F) -> Option<(usize, K)>
+where
+    T: Sync,
+    K: Ord + Send,
+    F: Fn(&T) -> K + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let best = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let ii = next.fetch_add(1, Ordering::SeqCst);
+
+                if ii >= candidates.len() {
+                    break;
+                }
+
+                let key = f(&candidates[ii]);
+                let mut best = best.lock().unwrap();
+
+                let better = match &*best {
+                    None => true,
+                    Some((jj, max)) => key > *max || (key == *max && ii < *jj),
+                };
+
+                if better {
+                    *best = Some((ii, key));
+                }
+            });
+        }
+    });
+
+    best.into_inner().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_first() {
+        let candidates: Vec<u64> = (0..1000).collect();
+
+        for threads in 1..5 {
+            assert_eq!(find_first(&candidates, threads, |x| Some(x * 2).filter(|y| y % 7 == 6)), Some((3, 6)));
+            assert_eq!(find_first(&candidates, threads, |_| None::<u64>), None);
+            assert_eq!(max_by_key(&candidates, threads, |x| x % 10), Some((9, 9)));
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::{Machine, Word};
+
+const HEADER: &str = "intcode-session 1";
+
+/// Something that happened during a run, in the order it happened.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Entry<W = i64> {
+    Input(W),
+    Output(W),
+    Halted,
+    Error(String),
+}
+
+impl<W: Word> fmt::Display for Entry<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Entry::Input(val) => write!(f, "in {}", val),
+            Entry::Output(val) => write!(f, "out {}", val),
+            Entry::Halted => write!(f, "halted"),
+            Entry::Error(msg) => write!(f, "error {}", msg),
+        }
+    }
+}
+
+/// An entry that differs between a recorded session and its replay. Either
+/// side is `None` past the end of its session.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence<W = i64> {
+    pub index: usize,
+    pub expected: Option<Entry<W>>,
+    pub actual: Option<Entry<W>>,
+}
+
+/// The transcript of a run: every value supplied to the program and every
+/// value it produced.
+///
+/// Sessions are saved as text, one entry per line:
+///
+/// ```text
+/// intcode-session 1
+/// in 5
+/// out 8834787
+/// halted
+/// ```
+///
+/// A run that fails ends with an `error <message>` line instead.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Session<W = i64> {
+    pub entries: Vec<Entry<W>>,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl<W: Word> Session<W> {
+    pub fn new() -> Self {
+        Session { entries: vec![] }
+    }
+
+    pub fn inputs(&self) -> Vec<W> {
+        let input = |entry: &Entry<W>| match entry {
+            Entry::Input(val) => Some(val.clone()),
+            _ => None,
+        };
+
+        self.entries.iter().filter_map(input).collect()
+    }
+
+    pub fn outputs(&self) -> Vec<W> {
+        let output = |entry: &Entry<W>| match entry {
+            Entry::Output(val) => Some(val.clone()),
+            _ => None,
+        };
+
+        self.entries.iter().filter_map(output).collect()
+    }
+
+    /// Runs the machine on `input` like `Machine::try_execute`, recording
+    /// the values it takes and produces. Values already queued on the
+    /// machine are taken first, and recorded too.
+    pub fn record(machine: &mut Machine<W>, input: impl Iterator<Item = W>) -> Self {
+        let entries = RefCell::new(vec![]);
+
+        let queued: Vec<W> = machine.input.drain(..).collect();
+        let input = queued.into_iter().chain(input).inspect(|val| entries.borrow_mut().push(Entry::Input(val.clone())));
+
+        for res in machine.try_execute(input) {
+            match res {
+                Ok(val) => entries.borrow_mut().push(Entry::Output(val)),
+                Err(e) => entries.borrow_mut().push(Entry::Error(e.to_string())),
+            }
+        }
+
+        let mut entries = entries.into_inner();
+
+        if machine.halted {
+            entries.push(Entry::Halted);
+        }
+
+        Session { entries }
+    }
+
+    /// Runs the machine on the inputs of this session, returning the new
+    /// session along with every entry in which it differs from this one.
+    pub fn replay(&self, machine: &mut Machine<W>) -> (Session<W>, Vec<Divergence<W>>) {
+        let actual = Session::record(machine, self.inputs().into_iter());
+        let len = self.entries.len().max(actual.entries.len());
+
+        let divergences = (0..len)
+            .map(|index| Divergence {
+                index,
+                expected: self.entries.get(index).cloned(),
+                actual: actual.entries.get(index).cloned(),
+            })
+            .filter(|d| d.expected != d.actual)
+            .collect();
+
+        (actual, divergences)
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+
+        for entry in &self.entries {
+            writeln!(writer, "{}", entry)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from(reader: impl BufRead) -> io::Result<Self> {
+        let mut lines = reader.lines();
+
+        match lines.next() {
+            Some(Ok(ref line)) if line == HEADER => {}
+            Some(Err(e)) => return Err(e),
+            _ => return Err(invalid("Not an Intcode session".to_string())),
+        }
+
+        let mut session = Session::new();
+
+        for line in lines {
+            let line = line?;
+            let mut parts = line.splitn(2, ' ');
+            let key = parts.next().unwrap();
+            let rest = parts.next().unwrap_or("");
+            let value = || rest.trim().parse().map_err(|_| invalid(format!("Invalid value in line: {}", line)));
+
+            let entry = match key {
+                "in" => Entry::Input(value()?),
+                "out" => Entry::Output(value()?),
+                "halted" => Entry::Halted,
+                "error" => Entry::Error(rest.to_string()),
+                "" => continue,
+                _ => return Err(invalid(format!("Unknown entry {}", key))),
+            };
+
+            session.entries.push(entry);
+        }
+
+        Ok(session)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Writes one line per divergence, such as
+/// `entry 3: expected out 5, got out 6`.
+pub fn write_report<W: Word>(divergences: &[Divergence<W>], out: &mut impl Write) -> io::Result<()> {
+    let describe = |entry: &Option<Entry<W>>| match entry {
+        Some(entry) => entry.to_string(),
+        None => "nothing".to_string(),
+    };
+
+    for d in divergences {
+        writeln!(out, "entry {}: expected {}, got {}", d.index, describe(&d.expected), describe(&d.actual))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Program;
+    use std::iter;
+
+    #[test]
+    fn test_replay() {
+        let mut program: Program = crate::parse(include_str!("../../day-05/input.txt")).unwrap();
+        let session = Session::record(&mut Machine::new(program.clone()), iter::once(5));
+
+        let mut buffer = vec![];
+        session.write_to(&mut buffer).unwrap();
+        let session = Session::read_from(&buffer[..]).unwrap();
+
+        assert_eq!(session.entries, vec![Entry::Input(5), Entry::Output(8834787), Entry::Halted]);
+        assert_eq!(session.replay(&mut Machine::new(program.clone())).1, vec![]);
+
+        // break the first instruction, which stores the input
+        program[0] = 0;
+        let (_, divergences) = session.replay(&mut Machine::new(program));
+
+        let mut report = vec![];
+        write_report(&divergences, &mut report).unwrap();
+
+        assert_eq!(
+            String::from_utf8(report).unwrap(),
+            "entry 0: expected in 5, got error Unknown instruction 0 at 0 [0]\n\
+             entry 1: expected out 8834787, got nothing\n\
+             entry 2: expected halted, got nothing\n"
+        );
+    }
+}
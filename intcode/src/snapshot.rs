@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::{Arithmetic, Memory, Word};
+
+const HEADER: &str = "intcode-snapshot 1";
+
+/// The complete state of a machine, apart from its tracer.
+///
+/// Snapshots are saved as text, one field per line:
+///
+/// ```text
+/// intcode-snapshot 1
+/// pc 12
+/// relative_base 0
+/// halted false
+/// arithmetic checked
+/// input 5,6
+/// limit 1000000
+/// memory 3,225,1,225,6,6,1105,...
+/// sparse 100000 42
+/// ```
+///
+/// `input` and `limit` are omitted when empty or unlimited; there is one
+/// `sparse` line per word stored outside the dense part of memory.
+#[derive(Clone, Debug)]
+pub struct Snapshot<W = i64> {
+    pub pc: usize,
+    pub relative_base: i64,
+    pub memory: Memory<W>,
+    pub input: VecDeque<W>,
+    pub arithmetic: Arithmetic,
+    pub halted: bool,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn parse_word<W: Word>(s: &str) -> io::Result<W> {
+    s.trim().parse().map_err(|_| invalid(format!("Invalid word {}", s)))
+}
+
+fn parse_list<W: Word>(s: &str) -> io::Result<Vec<W>> {
+    match s.trim() {
+        "" => Ok(vec![]),
+        s => s.split(',').map(parse_word).collect(),
+    }
+}
+
+fn join<'a, W: Word + 'a>(words: impl IntoIterator<Item = &'a W>) -> String {
+    words.into_iter().map(|w| w.to_string()).collect::<Vec<_>>().join(",")
+}
+
+impl<W: Word> Snapshot<W> {
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "pc {}", self.pc)?;
+        writeln!(writer, "relative_base {}", self.relative_base)?;
+        writeln!(writer, "halted {}", self.halted)?;
+
+        match self.arithmetic {
+            Arithmetic::Checked => writeln!(writer, "arithmetic checked")?,
+            Arithmetic::Wrapping => writeln!(writer, "arithmetic wrapping")?,
+        }
+
+        if !self.input.is_empty() {
+            writeln!(writer, "input {}", join(&self.input))?;
+        }
+
+        if self.memory.limit() != usize::MAX {
+            writeln!(writer, "limit {}", self.memory.limit())?;
+        }
+
+        writeln!(writer, "memory {}", join(self.memory.dense()))?;
+
+        let mut sparse: Vec<_> = self.memory.sparse().iter().collect();
+        sparse.sort_by_key(|(addr, _)| **addr);
+
+        for (addr, val) in sparse {
+            writeln!(writer, "sparse {} {}", addr, val)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from(reader: impl BufRead) -> io::Result<Self> {
+        let mut lines = reader.lines();
+
+        match lines.next() {
+            Some(Ok(ref line)) if line == HEADER => {}
+            Some(Err(e)) => return Err(e),
+            _ => return Err(invalid("Not an Intcode snapshot".to_string())),
+        }
+
+        let mut snapshot = Snapshot {
+            pc: 0,
+            relative_base: 0,
+            memory: Memory::new(vec![]),
+            input: VecDeque::new(),
+            arithmetic: Arithmetic::Checked,
+            halted: false,
+        };
+
+        let mut limit = usize::MAX;
+        let mut sparse = vec![];
+
+        for line in lines {
+            let line = line?;
+            let mut parts = line.splitn(2, ' ');
+            let key = parts.next().unwrap();
+            let value = parts.next().unwrap_or("");
+
+            let bad_value = || invalid(format!("Invalid value in line: {}", line));
+
+            match key {
+                "pc" => snapshot.pc = value.parse().map_err(|_| bad_value())?,
+                "relative_base" => snapshot.relative_base = value.parse().map_err(|_| bad_value())?,
+                "halted" => snapshot.halted = value.parse().map_err(|_| bad_value())?,
+                "arithmetic" => {
+                    snapshot.arithmetic = match value {
+                        "checked" => Arithmetic::Checked,
+                        "wrapping" => Arithmetic::Wrapping,
+                        _ => return Err(bad_value()),
+                    }
+                }
+                "input" => snapshot.input = parse_list(value)?.into(),
+                "limit" => limit = value.parse().map_err(|_| bad_value())?,
+                "memory" => snapshot.memory = Memory::new(parse_list(value)?),
+                "sparse" => {
+                    let mut parts = value.splitn(2, ' ');
+                    let addr: usize = parts.next().unwrap().parse().map_err(|_| bad_value())?;
+                    let val = parse_word(parts.next().ok_or_else(bad_value)?)?;
+                    sparse.push((addr, val));
+                }
+                "" => {}
+                _ => return Err(invalid(format!("Unknown field {}", key))),
+            }
+        }
+
+        for (addr, val) in sparse {
+            snapshot.memory.set(addr, val).map_err(|e| invalid(e.to_string()))?;
+        }
+
+        snapshot.memory.set_limit(limit);
+
+        Ok(snapshot)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Machine, Yield};
+
+    #[test]
+    fn test_round_trip() {
+        let mut machine = Machine::new(vec![3, 100000, 3, 20, 4, 100000, 4, 20, 99]);
+        machine.input.extend(vec![7, 8]);
+        machine.step().unwrap();
+
+        let mut buffer = vec![];
+        machine.snapshot().write_to(&mut buffer).unwrap();
+
+        let mut restored = Machine::new(vec![]);
+        restored.restore(Snapshot::read_from(&buffer[..]).unwrap());
+
+        assert_eq!(restored.pc, 2);
+        assert_eq!(restored.run(), Ok(Yield::Output(7)));
+        assert_eq!(restored.run(), Ok(Yield::Output(8)));
+        assert_eq!(restored.run(), Ok(Yield::Halted));
+    }
+}
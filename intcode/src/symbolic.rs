@@ -0,0 +1,429 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::ops::Range;
+
+use crate::{DecodeError, Instruction, Mode, Program};
+
+/// A value computed from symbolic inputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    Const(i64),
+    Var(String),
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    /// The contents of memory at a symbolic address.
+    Load(Box<Expr>),
+}
+
+impl Expr {
+    pub fn var(name: &str) -> Self {
+        Expr::Var(name.to_string())
+    }
+
+    pub fn as_const(&self) -> Option<i64> {
+        match self {
+            Expr::Const(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Adds two expressions, folding constants.
+    pub fn checked_add(a: Expr, b: Expr) -> Option<Expr> {
+        Some(match (a, b) {
+            (Expr::Const(x), Expr::Const(y)) => Expr::Const(x.checked_add(y)?),
+            (Expr::Const(0), e) | (e, Expr::Const(0)) => e,
+            (a, b) => Expr::Add(Box::new(a), Box::new(b)),
+        })
+    }
+
+    /// Multiplies two expressions, folding constants.
+    pub fn checked_mul(a: Expr, b: Expr) -> Option<Expr> {
+        Some(match (a, b) {
+            (Expr::Const(x), Expr::Const(y)) => Expr::Const(x.checked_mul(y)?),
+            (Expr::Const(0), _) | (_, Expr::Const(0)) => Expr::Const(0),
+            (Expr::Const(1), e) | (e, Expr::Const(1)) => e,
+            (a, b) => Expr::Mul(Box::new(a), Box::new(b)),
+        })
+    }
+
+    /// Expands the expression into a sum of monomials. Fails for loads
+    /// from symbolic addresses, and on overflow.
+    pub fn to_polynomial(&self) -> Option<Polynomial> {
+        match self {
+            Expr::Const(x) => Some(Polynomial::constant(*x)),
+            Expr::Var(name) => Some(Polynomial {
+                terms: vec![(vec![name.clone()], 1)].into_iter().collect(),
+            }),
+            Expr::Add(a, b) => a.to_polynomial()?.checked_add(&b.to_polynomial()?),
+            Expr::Mul(a, b) => a.to_polynomial()?.checked_mul(&b.to_polynomial()?),
+            Expr::Load(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Const(x) => write!(f, "{}", x),
+            Expr::Var(name) => write!(f, "{}", name),
+            Expr::Add(a, b) => write!(f, "({} + {})", a, b),
+            Expr::Mul(a, b) => write!(f, "{} * {}", a, b),
+            Expr::Load(addr) => write!(f, "[{}]", addr),
+        }
+    }
+}
+
+/// A polynomial with integer coefficients. Each monomial is the sorted list
+/// of the variables multiplied in it; the constant term has none.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial {
+    pub terms: BTreeMap<Vec<String>, i64>,
+}
+
+impl Polynomial {
+    pub fn constant(x: i64) -> Self {
+        let mut terms = BTreeMap::new();
+
+        if x != 0 {
+            terms.insert(vec![], x);
+        }
+
+        Polynomial { terms }
+    }
+
+    pub fn checked_add(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut terms = self.terms.clone();
+
+        for (monomial, coeff) in &other.terms {
+            let sum = terms.get(monomial).unwrap_or(&0).checked_add(*coeff)?;
+
+            match sum {
+                0 => terms.remove(monomial),
+                _ => terms.insert(monomial.clone(), sum),
+            };
+        }
+
+        Some(Polynomial { terms })
+    }
+
+    pub fn checked_mul(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut res = Polynomial::constant(0);
+
+        for (m0, c0) in &self.terms {
+            for (m1, c1) in &other.terms {
+                let mut monomial: Vec<String> = m0.iter().chain(m1).cloned().collect();
+                monomial.sort();
+
+                let term = Polynomial {
+                    terms: vec![(monomial, c0.checked_mul(*c1)?)].into_iter().collect(),
+                };
+
+                res = res.checked_add(&term)?;
+            }
+        }
+
+        Some(res)
+    }
+
+    /// The highest power of `var` in any monomial.
+    pub fn degree_in(&self, var: &str) -> usize {
+        let power = |monomial: &Vec<String>| monomial.iter().filter(|v| *v == var).count();
+        self.terms.keys().map(power).max().unwrap_or(0)
+    }
+
+    /// Evaluates the polynomial, treating variables missing from `values` as
+    /// zero.
+    pub fn eval(&self, values: &BTreeMap<&str, i64>) -> Option<i64> {
+        self.terms.iter().try_fold(0i64, |sum, (monomial, coeff)| {
+            let term = monomial
+                .iter()
+                .try_fold(*coeff, |acc, var| acc.checked_mul(*values.get(var.as_str()).unwrap_or(&0)))?;
+            sum.checked_add(term)
+        })
+    }
+
+    /// Finds all assignments of the variables in `domains` for which the
+    /// polynomial equals `target`, in lexicographic order of the values
+    /// listed in the order of `domains`.
+    ///
+    /// If the polynomial is linear in one of the variables, that variable is
+    /// solved for directly and only the others are enumerated.
+    pub fn solve(&self, target: i64, domains: &[(&str, Range<i64>)]) -> Vec<Vec<i64>> {
+        let linear = domains.iter().rposition(|(var, _)| self.degree_in(var) == 1);
+        let mut solutions = vec![];
+
+        self.enumerate(target, domains, linear, &mut BTreeMap::new(), &mut solutions);
+
+        solutions.sort();
+        solutions
+    }
+
+    fn enumerate<'a>(
+        &self,
+        target: i64,
+        domains: &[(&'a str, Range<i64>)],
+        linear: Option<usize>,
+        values: &mut BTreeMap<&'a str, i64>,
+        solutions: &mut Vec<Vec<i64>>,
+    ) {
+        let assigned = |values: &BTreeMap<&str, i64>| domains.iter().map(|(var, _)| values[var]).collect();
+
+        let next = (0..domains.len()).find(|ii| Some(*ii) != linear && !values.contains_key(domains[*ii].0));
+
+        if let Some(ii) = next {
+            let (var, range) = &domains[ii];
+
+            for x in range.clone() {
+                values.insert(var, x);
+                self.enumerate(target, domains, linear, values, solutions);
+            }
+
+            values.remove(var);
+            return;
+        }
+
+        let (var, range) = match linear {
+            Some(ii) => &domains[ii],
+            None => {
+                if self.eval(values) == Some(target) {
+                    solutions.push(assigned(values));
+                }
+
+                return;
+            }
+        };
+
+        // with everything else fixed, the polynomial is `a * var + b`
+        values.insert(var, 0);
+        let b = self.eval(values);
+        values.insert(var, 1);
+        let a = self.eval(values).zip(b).and_then(|(ab, b)| ab.checked_sub(b));
+
+        let candidates: Vec<i64> = match (a, b.and_then(|b| target.checked_sub(b))) {
+            (Some(0), Some(0)) => range.clone().collect(),
+            (Some(a), Some(rest)) if rest.checked_rem(a) == Some(0) => vec![rest / a],
+            _ => vec![],
+        };
+
+        for x in candidates.into_iter().filter(|x| range.contains(x)) {
+            values.insert(var, x);
+            solutions.push(assigned(values));
+        }
+
+        values.remove(var);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SymbolicError {
+    Decode { pc: usize, error: DecodeError },
+    /// An opcode, address, jump condition or comparison depends on a
+    /// symbolic value.
+    Symbolic { pc: usize },
+    NegativeAddress { pc: usize },
+    ValueModeWrite { pc: usize },
+    Overflow { pc: usize },
+    NeedsInput { pc: usize },
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolicError::Decode { pc, error } => write!(f, "{} at {}", error, pc),
+            SymbolicError::Symbolic { pc } => write!(f, "Symbolic value used as a concrete one at {}", pc),
+            SymbolicError::NegativeAddress { pc } => write!(f, "Negative address at {}", pc),
+            SymbolicError::ValueModeWrite { pc } => write!(f, "Write to parameter in value mode at {}", pc),
+            SymbolicError::Overflow { pc } => write!(f, "Overflow at {}", pc),
+            SymbolicError::NeedsInput { pc } => write!(f, "No input left at {}", pc),
+        }
+    }
+}
+
+impl std::error::Error for SymbolicError {}
+
+/// Runs a program over symbolic values. Control flow and written addresses
+/// must stay concrete; arithmetic on symbolic values builds expressions.
+///
+/// Memory is kept in a map, so writes to far-away addresses cost no more
+/// than any other.
+pub struct SymbolicMachine {
+    pub pc: usize,
+    pub relative_base: i64,
+    pub memory: HashMap<usize, Expr>,
+    pub input: VecDeque<Expr>,
+    pub output: Vec<Expr>,
+    pub halted: bool,
+}
+
+impl SymbolicMachine {
+    pub fn new(program: &Program) -> Self {
+        SymbolicMachine {
+            pc: 0,
+            relative_base: 0,
+            memory: program.iter().map(|x| Expr::Const(*x)).enumerate().collect(),
+            input: VecDeque::new(),
+            output: vec![],
+            halted: false,
+        }
+    }
+
+    pub fn get(&self, addr: usize) -> Expr {
+        self.memory.get(&addr).cloned().unwrap_or(Expr::Const(0))
+    }
+
+    pub fn set(&mut self, addr: usize, val: Expr) {
+        self.memory.insert(addr, val);
+    }
+
+    fn concrete(&self, val: &Expr) -> Result<i64, SymbolicError> {
+        val.as_const().ok_or(SymbolicError::Symbolic { pc: self.pc })
+    }
+
+    fn addr(&self, mode: Mode, param: usize) -> Result<usize, SymbolicError> {
+        let param = self.concrete(&self.get(self.pc + param))?;
+
+        let addr = match mode {
+            Mode::Position => param,
+            Mode::Relative => self.relative(param)?,
+            Mode::Value => return Err(SymbolicError::ValueModeWrite { pc: self.pc }),
+        };
+
+        match addr {
+            x if x < 0 => Err(SymbolicError::NegativeAddress { pc: self.pc }),
+            x => Ok(x as usize),
+        }
+    }
+
+    fn relative(&self, offset: i64) -> Result<i64, SymbolicError> {
+        self.relative_base.checked_add(offset).ok_or(SymbolicError::Overflow { pc: self.pc })
+    }
+
+    /// Reads a parameter. Reading from a symbolic position gives a `Load`,
+    /// which is fine as long as the result does not matter in the end.
+    fn read(&self, mode: Mode, param: usize) -> Result<Expr, SymbolicError> {
+        let val = self.get(self.pc + param);
+
+        match (mode, val.as_const()) {
+            (Mode::Value, _) => Ok(val),
+            (Mode::Position, None) => Ok(Expr::Load(Box::new(val))),
+            _ => Ok(self.get(self.addr(mode, param)?)),
+        }
+    }
+
+    /// Runs the program until it halts.
+    pub fn run(&mut self) -> Result<(), SymbolicError> {
+        while !self.halted {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn step(&mut self) -> Result<(), SymbolicError> {
+        let raw = self.concrete(&self.get(self.pc))?;
+        let instruction = Instruction::parse(raw).map_err(|error| SymbolicError::Decode { pc: self.pc, error })?;
+        let overflow = SymbolicError::Overflow { pc: self.pc };
+
+        match instruction {
+            Instruction::Add(mode0, mode1, mode2) | Instruction::Multiply(mode0, mode1, mode2) => {
+                let (a, b) = (self.read(mode0, 1)?, self.read(mode1, 2)?);
+                let dest = self.addr(mode2, 3)?;
+
+                let val = match instruction {
+                    Instruction::Add(..) => Expr::checked_add(a, b),
+                    _ => Expr::checked_mul(a, b),
+                };
+
+                self.set(dest, val.ok_or(overflow)?);
+            }
+            Instruction::LessThan(mode0, mode1, mode2) | Instruction::Equals(mode0, mode1, mode2) => {
+                let a = self.concrete(&self.read(mode0, 1)?)?;
+                let b = self.concrete(&self.read(mode1, 2)?)?;
+                let dest = self.addr(mode2, 3)?;
+
+                let val = match instruction {
+                    Instruction::LessThan(..) => a < b,
+                    _ => a == b,
+                };
+
+                self.set(dest, Expr::Const(val as i64));
+            }
+            Instruction::Input(mode0) => {
+                let dest = self.addr(mode0, 1)?;
+
+                match self.input.pop_front() {
+                    Some(val) => self.set(dest, val),
+                    None => return Err(SymbolicError::NeedsInput { pc: self.pc }),
+                }
+            }
+            Instruction::Output(mode0) => {
+                let val = self.read(mode0, 1)?;
+                self.output.push(val);
+            }
+            Instruction::JumpIfTrue(mode0, mode1) | Instruction::JumpIfFalse(mode0, mode1) => {
+                let cond = self.concrete(&self.read(mode0, 1)?)? != 0;
+                let target = self.concrete(&self.read(mode1, 2)?)?;
+
+                if cond == matches!(instruction, Instruction::JumpIfTrue(..)) {
+                    if target < 0 {
+                        return Err(SymbolicError::NegativeAddress { pc: self.pc });
+                    }
+
+                    self.pc = target as usize;
+                    return Ok(());
+                }
+            }
+            Instruction::AdjustBase(mode0) => {
+                self.relative_base = self.relative(self.concrete(&self.read(mode0, 1)?)?)?;
+            }
+            Instruction::Halt => {
+                self.halted = true;
+                return Ok(());
+            }
+        }
+
+        self.pc += instruction.size();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        // like day 2, the first instruction reads from the symbolic addresses,
+        // but its result is overwritten; [0] = (a + 3) * b + 1
+        let mut machine = SymbolicMachine::new(&vec![1, 0, 0, 3, 1001, 1, 3, 3, 2, 3, 2, 3, 1001, 3, 1, 0, 99]);
+        machine.set(1, Expr::var("a"));
+        machine.set(2, Expr::var("b"));
+        machine.run().unwrap();
+
+        let result = machine.get(0);
+        assert_eq!(result.to_string(), "((a + 3) * b + 1)");
+
+        let poly = result.to_polynomial().unwrap();
+        assert_eq!(poly.solve(13, &[("a", 0..10), ("b", 0..10)]), vec![vec![0, 4], vec![1, 3], vec![3, 2], vec![9, 1]]);
+
+        // not linear in anything, so every assignment is tried
+        let square = Expr::checked_mul(Expr::var("x"), Expr::var("x")).unwrap().to_polynomial().unwrap();
+        assert_eq!(square.solve(49, &[("x", -10..10)]), vec![vec![-7], vec![7]]);
+
+        // -x = i64::MIN has no solution in i64
+        let negated = Expr::checked_mul(Expr::Const(-1), Expr::var("x")).unwrap().to_polynomial().unwrap();
+        assert_eq!(negated.solve(i64::MIN, &[("x", 0..10)]), Vec::<Vec<i64>>::new());
+    }
+
+    #[test]
+    fn test_errors() {
+        let run = |program: Vec<i64>| {
+            let mut machine = SymbolicMachine::new(&program);
+            machine.run().map(|_| machine)
+        };
+
+        assert_eq!(run(vec![11101, 1, 1, 0, 99]).err(), Some(SymbolicError::ValueModeWrite { pc: 0 }));
+        assert_eq!(run(vec![109, i64::MAX, 109, 1, 99]).err(), Some(SymbolicError::Overflow { pc: 2 }));
+        assert_eq!(run(vec![1101, 2, 3, 1_000_000_000_000, 99]).unwrap().get(1_000_000_000_000), Expr::Const(5));
+    }
+}
@@ -0,0 +1,391 @@
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use crate::extension::MAX_PARAMS;
+use crate::{Instruction, Mode, Word};
+
+/// What an executed instruction was: a builtin one, or an extension
+/// identified by its instruction word.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Builtin(Instruction),
+    Extension { word: i64, mnemonic: String },
+}
+
+impl Operation {
+    pub fn mnemonic(&self) -> &str {
+        match self {
+            Operation::Builtin(instruction) => instruction.mnemonic(),
+            Operation::Extension { mnemonic, .. } => mnemonic,
+        }
+    }
+
+    /// Modes of the first `count` parameters. A builtin instruction always
+    /// has all of its own.
+    pub fn modes(&self, count: usize) -> Vec<Mode> {
+        match self {
+            Operation::Builtin(instruction) => instruction.modes(),
+            Operation::Extension { word, .. } => (0..count)
+                .map(|ii| Mode::parse(word / 10_i64.pow(ii as u32 + 2) % 10).unwrap_or(Mode::Position))
+                .collect(),
+        }
+    }
+}
+
+/// One executed instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event<W = i64> {
+    pub pc: usize,
+    pub operation: Operation,
+    /// The raw parameter words, as stored after the opcode.
+    pub params: Vec<W>,
+    /// The value each parameter resolved to; `None` for written ones.
+    pub values: Vec<Option<W>>,
+    /// The addresses written to and the values stored there, in order.
+    pub writes: Vec<(usize, W)>,
+}
+
+/// Receives every instruction executed by a machine while tracing is on.
+pub trait Tracer<W> {
+    fn record(&mut self, event: &Event<W>);
+
+    /// Flushes buffered output, reporting the first error encountered while
+    /// recording.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W, F: FnMut(&Event<W>)> Tracer<W> for F {
+    fn record(&mut self, event: &Event<W>) {
+        self(event)
+    }
+}
+
+/// Formats an event as a single line, e.g. `4: add [9]=30, 40, [3] -> [3]=70`.
+pub fn format_event<W: Word>(event: &Event<W>) -> String {
+    let operands: Vec<String> = event
+        .operation
+        .modes(event.params.len())
+        .into_iter()
+        .zip(&event.params)
+        .zip(&event.values)
+        .map(|((mode, param), value)| {
+            let operand = match mode {
+                Mode::Position => format!("[{}]", param),
+                Mode::Value => format!("{}", param),
+                Mode::Relative => format!("[rb{:+}]", param),
+            };
+
+            match (mode, value) {
+                (Mode::Position, Some(value)) | (Mode::Relative, Some(value)) => format!("{}={}", operand, value),
+                _ => operand,
+            }
+        })
+        .collect();
+
+    let mut line = format!("{}: {} {}", event.pc, event.operation.mnemonic(), operands.join(", "));
+
+    for (addr, value) in &event.writes {
+        line += &format!(" -> [{}]={}", addr, value);
+    }
+
+    line.trim_end().to_string()
+}
+
+/// Writes one line of text per event.
+pub struct TextTracer<T: Write> {
+    writer: T,
+    error: Option<io::Error>,
+}
+
+impl<T: Write> TextTracer<T> {
+    pub fn new(writer: T) -> Self {
+        TextTracer { writer, error: None }
+    }
+}
+
+impl<T: Write, W: Word> Tracer<W> for TextTracer<T> {
+    fn record(&mut self, event: &Event<W>) {
+        if self.error.is_none() {
+            self.error = writeln!(self.writer, "{}", format_event(event)).err();
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+const MAGIC: &[u8] = b"ICTR\x01";
+
+fn write_uvarint(writer: &mut impl Write, mut val: u64) -> io::Result<()> {
+    loop {
+        let byte = (val & 0x7f) as u8;
+        val >>= 7;
+
+        if val == 0 {
+            return writer.write_all(&[byte]);
+        }
+
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn write_svarint(writer: &mut impl Write, val: i64) -> io::Result<()> {
+    write_uvarint(writer, ((val << 1) ^ (val >> 63)) as u64)
+}
+
+fn read_uvarint(reader: &mut impl Read) -> io::Result<Option<u64>> {
+    let mut val = 0;
+
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+
+        if reader.read(&mut byte)? == 0 {
+            return match shift {
+                0 => Ok(None),
+                _ => Err(io::ErrorKind::UnexpectedEof.into()),
+            };
+        }
+
+        val |= u64::from(byte[0] & 0x7f) << shift;
+
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(val));
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::InvalidData, "varint too long"))
+}
+
+fn read_svarint(reader: &mut impl Read) -> io::Result<i64> {
+    match read_uvarint(reader)? {
+        Some(val) => Ok((val >> 1) as i64 ^ -((val & 1) as i64)),
+        None => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+/// Writes events in a compact binary format: after a short header, each
+/// event is a sequence of LEB128 varints (signed ones zigzag-encoded) holding
+/// the pc, the instruction word, the parameters, the resolved values of the
+/// read parameters and, for each write, the address and new value.
+///
+/// Extension instructions are told apart by their opcode. After the
+/// instruction word, they also hold the length and bytes of the mnemonic,
+/// the number of parameters, and a bit mask of the written ones.
+pub struct BinaryTracer<T: Write> {
+    writer: T,
+    error: Option<io::Error>,
+}
+
+impl<T: Write> BinaryTracer<T> {
+    pub fn new(mut writer: T) -> Self {
+        let error = writer.write_all(MAGIC).err();
+        BinaryTracer { writer, error }
+    }
+
+    fn write(&mut self, event: &Event) -> io::Result<()> {
+        let w = &mut self.writer;
+
+        write_uvarint(w, event.pc as u64)?;
+
+        match &event.operation {
+            Operation::Builtin(instruction) => write_svarint(w, instruction.encode())?,
+            Operation::Extension { word, mnemonic } => {
+                write_svarint(w, *word)?;
+                write_uvarint(w, mnemonic.len() as u64)?;
+                w.write_all(mnemonic.as_bytes())?;
+                write_uvarint(w, event.params.len() as u64)?;
+
+                let mask = event.values.iter().enumerate().filter(|(_, value)| value.is_none());
+                write_uvarint(w, mask.map(|(ii, _)| 1 << ii).sum())?;
+            }
+        }
+
+        for param in &event.params {
+            write_svarint(w, *param)?;
+        }
+
+        for value in event.values.iter().flatten() {
+            write_svarint(w, *value)?;
+        }
+
+        for (addr, value) in &event.writes {
+            write_uvarint(w, *addr as u64)?;
+            write_svarint(w, *value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Write> Tracer<i64> for BinaryTracer<T> {
+    fn record(&mut self, event: &Event) {
+        if self.error.is_none() {
+            self.error = self.write(event).err();
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+/// Reads back a trace written by `BinaryTracer`.
+pub fn read_binary(mut reader: impl Read) -> io::Result<Vec<Event>> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let mut magic = [0; 5];
+    reader.read_exact(&mut magic)?;
+
+    if magic != MAGIC {
+        return Err(invalid("not an Intcode trace"));
+    }
+
+    let mut events = vec![];
+
+    while let Some(pc) = read_uvarint(&mut reader)? {
+        let word = read_svarint(&mut reader)?;
+
+        // which parameters are written, by index
+        let (operation, written): (Operation, Vec<bool>) = match Instruction::parse(word % 100) {
+            Ok(_) => {
+                let instruction = Instruction::parse(word).map_err(|_| invalid("bad opcode"))?;
+                let written = (0..instruction.size() - 1).map(|ii| Some(ii) == instruction.written_param()).collect();
+                (Operation::Builtin(instruction), written)
+            }
+            Err(_) => {
+                let mut field = || read_uvarint(&mut reader)?.ok_or_else(|| invalid("truncated event"));
+
+                let len = usize::try_from(field()?).map_err(|_| invalid("bad mnemonic"))?;
+                let mut mnemonic = vec![0; len];
+                reader.read_exact(&mut mnemonic)?;
+                let mnemonic = String::from_utf8(mnemonic).map_err(|_| invalid("bad mnemonic"))?;
+
+                let mut field = || read_uvarint(&mut reader)?.ok_or_else(|| invalid("truncated event"));
+                let (count, mask) = (field()?, field()?);
+
+                if count > MAX_PARAMS as u64 {
+                    return Err(invalid("too many parameters"));
+                }
+
+                (Operation::Extension { word, mnemonic }, (0..count).map(|ii| mask >> ii & 1 == 1).collect())
+            }
+        };
+
+        let mut params = vec![];
+        for _ in &written {
+            params.push(read_svarint(&mut reader)?);
+        }
+
+        let mut values = vec![];
+        for is_written in &written {
+            values.push(match is_written {
+                true => None,
+                false => Some(read_svarint(&mut reader)?),
+            });
+        }
+
+        let mut writes = vec![];
+        for _ in written.iter().filter(|is_written| **is_written) {
+            let addr = read_uvarint(&mut reader)?.ok_or_else(|| invalid("truncated event"))?;
+            writes.push((addr as usize, read_svarint(&mut reader)?));
+        }
+
+        events.push(Event {
+            pc: pc as usize,
+            operation,
+            params,
+            values,
+            writes,
+        });
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::{Extension, Extensions, Param};
+    use crate::Machine;
+    use std::iter;
+    use std::sync::{Arc, Mutex};
+
+    /// A writer that can be inspected after handing it to a tracer.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn trace(tracer: impl Tracer<i64> + Send + Sync + 'static) {
+        // outputs a copy of itself
+        let program = vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+
+        let mut machine = Machine::new(program.clone());
+        machine.tracer = Some(Box::new(tracer));
+        machine.tracing = true;
+
+        assert_eq!(machine.execute(iter::empty()).collect::<Vec<_>>(), program);
+        machine.tracer.as_mut().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let (text, binary) = (Shared::default(), Shared::default());
+        trace(TextTracer::new(text.clone()));
+        trace(BinaryTracer::new(binary.clone()));
+
+        let text = String::from_utf8(text.0.lock().unwrap().clone()).unwrap();
+        let events = read_binary(&binary.0.lock().unwrap()[..]).unwrap();
+        let lines: Vec<String> = events.iter().map(format_event).collect();
+
+        assert_eq!(lines, text.lines().collect::<Vec<_>>());
+        assert_eq!(lines[0], "0: arb 1");
+        assert_eq!(lines[1], "2: out [rb-1]=109");
+        assert_eq!(lines[2], "4: add [100]=0, 1, [100] -> [100]=1");
+        assert_eq!(lines[4], "12: jf [101]=0, 0");
+
+        for val in [0, 1, -1, 63, -64, 64, i64::MAX, i64::MIN] {
+            let mut buffer = vec![];
+            write_svarint(&mut buffer, val).unwrap();
+            assert_eq!(read_svarint(&mut &buffer[..]).unwrap(), val);
+        }
+    }
+
+    #[test]
+    fn test_extension_events() {
+        let mut extensions = Extensions::new();
+        let pair = Extension::new("pair", &[Param::Read, Param::Write, Param::Write], |args: &[i64]| {
+            Ok(vec![args[0], -args[0]])
+        });
+        extensions.register(11, pair).unwrap();
+
+        // pair 5, [5], [rb+6]; hlt
+        let binary = Shared::default();
+        let mut machine = Machine::new(vec![20111, 5, 5, 6, 99, 0, 0]);
+        machine.extensions = Some(Arc::new(extensions));
+        machine.tracer = Some(Box::new(BinaryTracer::new(binary.clone())));
+        machine.tracing = true;
+        for _ in machine.execute(iter::empty()) {}
+
+        let events = read_binary(&binary.0.lock().unwrap()[..]).unwrap();
+        let lines: Vec<String> = events.iter().map(format_event).collect();
+        assert_eq!(lines, vec!["0: pair 5, [5], [rb+6] -> [5]=5 -> [6]=-5", "4: hlt"]);
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+/// A value stored in a memory cell of the machine.
+pub trait Word: Clone + Debug + Display + PartialEq + PartialOrd + FromStr + Send + Sync + 'static {
+    fn from_i64(val: i64) -> Self;
+    fn to_i64(&self) -> Option<i64>;
+
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    fn wrapping_add(&self, other: &Self) -> Self;
+    fn wrapping_mul(&self, other: &Self) -> Self;
+
+    fn is_zero(&self) -> bool {
+        self.to_i64() == Some(0)
+    }
+}
+
+impl Word for i64 {
+    fn from_i64(val: i64) -> Self {
+        val
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        Some(*self)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        i64::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        i64::checked_mul(*self, *other)
+    }
+
+    fn wrapping_add(&self, other: &Self) -> Self {
+        i64::wrapping_add(*self, *other)
+    }
+
+    fn wrapping_mul(&self, other: &Self) -> Self {
+        i64::wrapping_mul(*self, *other)
+    }
+}
+
+impl Word for BigInt {
+    fn from_i64(val: i64) -> Self {
+        BigInt::from(val)
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        ToPrimitive::to_i64(self)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn wrapping_add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn wrapping_mul(&self, other: &Self) -> Self {
+        self * other
+    }
+}